@@ -0,0 +1,54 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+
+const LINES: usize = 1_000_000;
+
+// --------------------------------------------------
+fn gen_input() -> PathBuf {
+    let path = env::temp_dir().join(format!("catr-bench-{}.txt", std::process::id()));
+    let mut file = BufWriter::new(File::create(&path).unwrap());
+    for i in 0..LINES {
+        writeln!(file, "{i:>8} the quick brown fox jumps over the lazy dog").unwrap();
+    }
+    file.flush().unwrap();
+    path
+}
+
+// --------------------------------------------------
+fn null() -> File {
+    File::options().write(true).open("/dev/null").unwrap()
+}
+
+// --------------------------------------------------
+/// The `BufRead::lines()` loop catr used before it had a passthrough mode.
+fn line_based(input: File, output: File) {
+    let mut output = BufWriter::new(output);
+    for line in BufReader::new(input).lines() {
+        writeln!(output, "{}", line.unwrap()).unwrap();
+    }
+    output.flush().unwrap();
+}
+
+// --------------------------------------------------
+fn bench_copy(c: &mut Criterion) {
+    let path = gen_input();
+    let mut group = c.benchmark_group("copy");
+    group.throughput(Throughput::Bytes(fs::metadata(&path).unwrap().len()));
+    group.bench_function("kernel", |b| {
+        b.iter(|| catr::copy::copy(&mut File::open(&path).unwrap(), &mut null()).unwrap())
+    });
+    group.bench_function("buffered", |b| {
+        b.iter(|| catr::copy::buffered_copy(&mut File::open(&path).unwrap(), &mut null()).unwrap())
+    });
+    group.bench_function("line_based", |b| {
+        b.iter(|| line_based(File::open(&path).unwrap(), null()))
+    });
+    group.finish();
+    fs::remove_file(&path).unwrap();
+}
+
+criterion_group!(benches, bench_copy);
+criterion_main!(benches);
@@ -11,6 +11,8 @@ EMPTY="$ROOT/empty.txt"
 FOX="$ROOT/fox.txt"
 SPIDERS="$ROOT/spiders.txt"
 BUSTLE="$ROOT/the-bustle.txt"
+CONTROLS="$ROOT/controls.txt"
+BINARY="$ROOT/binary.txt"
 ALL="$EMPTY $FOX $SPIDERS $BUSTLE"
 
 for FILE in $ALL; do
@@ -28,3 +30,43 @@ cat    < $BUSTLE > $OUT_DIR/$(basename $BUSTLE).stdin.out
 cat -n < $BUSTLE > $OUT_DIR/$(basename $BUSTLE).n.stdin.out
 cat -b < $BUSTLE > $OUT_DIR/$(basename $BUSTLE).b.stdin.out
 
+
+for FLAG in A E T v s e t; do
+    cat -$FLAG $CONTROLS > $OUT_DIR/$(basename $CONTROLS).$FLAG.out
+done
+cat -ns $CONTROLS > $OUT_DIR/$(basename $CONTROLS).ns.out
+cat -bA $CONTROLS > $OUT_DIR/$(basename $CONTROLS).bA.out
+
+cat $BINARY > $OUT_DIR/$(basename $BINARY).out
+for FLAG in n b A s; do
+    cat -$FLAG $BINARY > $OUT_DIR/$(basename $BINARY).$FLAG.out
+done
+
+gzip -dc $ROOT/all.txt.gz | cat -n > $OUT_DIR/all.txt.gz.n.out
+
+LOG="$ROOT/log.txt"
+cat -n $LOG | sed -n '5,8p' > $OUT_DIR/log.txt.lines.n.out
+cat -n $LOG | sed -n '3,4p;20,$p' > $OUT_DIR/log.txt.lines-open.n.out
+sed -n '/^BEGIN/,/^END/{p;/^END/q}' $LOG > $OUT_DIR/log.txt.from-to.out
+cat -n $LOG | sed -n '11,19p' > $OUT_DIR/log.txt.from-to.C2.n.out
+cat -b $LOG | sed -n '13,17p' > $OUT_DIR/log.txt.from-to.b.out
+
+ZEROS="$ROOT/zeros.bin"
+hexdump -C $FOX > $OUT_DIR/fox.txt.hex.out
+hexdump -C $ZEROS > $OUT_DIR/zeros.bin.hex.out
+od -A o -t o1z $ZEROS > $OUT_DIR/zeros.bin.octal.out
+od -A o -t o1z -j 5 -N 40 -w8 $BUSTLE > $OUT_DIR/the-bustle.txt.octal.j5N40w8.out
+
+tac $BUSTLE > $OUT_DIR/the-bustle.txt.reverse.out
+tac $BUSTLE | cat -n > $OUT_DIR/the-bustle.txt.reverse.n.out
+tac -s ' ' $FOX > $OUT_DIR/fox.txt.reverse.space.out
+tac -r -s '[ .]' $FOX > $OUT_DIR/fox.txt.reverse.regex.out
+
+SECTIONS="$ROOT/sections.txt"
+nl -ba -v10 -i5 $BUSTLE > $OUT_DIR/the-bustle.txt.n.v10i5.out
+nl -ba -nrz -w3 -s': ' $SPIDERS > $OUT_DIR/spiders.txt.n.rz.out
+nl -ha -fa $SECTIONS > $OUT_DIR/sections.txt.nl.out
+nl -bp'^b[13]' $SECTIONS > $OUT_DIR/sections.txt.bp.out
+
+JAPANESE="$ROOT/japanese.txt"
+cat -n $JAPANESE > $OUT_DIR/japanese.txt.n.out
@@ -0,0 +1,537 @@
+//! The line transforms of cat, usable on any reader and writer.
+
+use crate::highlight::{self, Style, Syntax};
+use crate::select::{HeldLine, SelectState, Selection};
+use crate::CatError;
+use regex::bytes::Regex;
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+/// Which lines get a line number.
+#[derive(Debug, Default, Clone)]
+pub enum Numbering {
+    #[default]
+    None,
+    /// Every line, like `cat -n`.
+    All,
+    /// Lines that aren't empty, like `cat -b`.
+    NonBlank,
+    /// Lines matching a regular expression, like `nl -bpREGEX`.
+    Matching(Regex),
+}
+
+/// How line numbers are laid out in their column.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NumberFormat {
+    /// Left justified, like `nl -n ln`.
+    Left,
+    /// Right justified, like `nl -n rn`.
+    #[default]
+    Right,
+    /// Right justified with leading zeros, like `nl -n rz`.
+    RightZero,
+}
+
+/// The part of a logical page a line is in, when sections are recognized.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Header,
+    #[default]
+    Body,
+    Footer,
+}
+
+/// Copies input to output with the options of cat.
+///
+/// ```
+/// use catr::{Cat, Numbering};
+///
+/// let mut out = vec![];
+/// Cat::new()
+///     .number(Numbering::All)
+///     .squeeze_blank(true)
+///     .copy(&b"one\n\n\n\ttwo\n"[..], &mut out)
+///     .unwrap();
+/// assert_eq!(out, b"     1\tone\n     2\t\n     3\t\ttwo\n");
+/// ```
+#[derive(Debug)]
+pub struct Cat {
+    number: Numbering,
+    start: i64,
+    increment: i64,
+    width: usize,
+    number_format: NumberFormat,
+    number_separator: String,
+    pad_unnumbered: bool,
+    section_delimiter: Option<Vec<u8>>,
+    header: Numbering,
+    footer: Numbering,
+    squeeze_blank: bool,
+    show_ends: bool,
+    show_tabs: bool,
+    show_nonprinting: bool,
+    pub(crate) color: bool,
+    pub(crate) selection: Option<Selection>,
+}
+
+/// A writer applying the options of a [`Cat`] to everything written to it
+/// before passing it on. Lines may be split across writes.
+///
+/// ```
+/// use catr::Cat;
+/// use std::io::Write;
+///
+/// let cat = Cat::new().show_ends(true);
+/// let mut writer = cat.writer(vec![]);
+/// writer.write_all(b"half a li").unwrap();
+/// writer.write_all(b"ne\nand more\n").unwrap();
+/// assert_eq!(writer.into_inner().unwrap(), b"half a line$\nand more$\n");
+/// ```
+#[derive(Debug)]
+pub struct CatWriter<'a, W: Write> {
+    cat: &'a Cat,
+    inner: W,
+    state: LineState,
+}
+
+/// Numbering and squeezing state carried from one line to the next, and
+/// across reopened files when following.
+#[derive(Debug, Default)]
+pub(crate) struct LineState {
+    lines_numbered: usize,
+    prev_blank: bool,
+    /// The start of a line whose end hasn't been read yet.
+    pending: Vec<u8>,
+    lines_read: usize,
+    /// Lines read that get a number, counted when a selection is in effect.
+    numbered_read: usize,
+    section: Section,
+    select: SelectState,
+}
+
+impl Default for Cat {
+    fn default() -> Self {
+        Cat {
+            number: Numbering::None,
+            start: 1,
+            increment: 1,
+            width: 6,
+            number_format: NumberFormat::Right,
+            number_separator: "\t".to_string(),
+            pad_unnumbered: false,
+            section_delimiter: None,
+            header: Numbering::None,
+            footer: Numbering::None,
+            show_ends: false,
+            show_tabs: false,
+            show_nonprinting: false,
+            squeeze_blank: false,
+            color: false,
+            selection: None,
+        }
+    }
+}
+
+impl Cat {
+    /// A `Cat` that copies its input unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Numbers output lines, or the body lines of each logical page when
+    /// sections are recognized.
+    pub fn number(mut self, number: Numbering) -> Self {
+        self.number = number;
+        self
+    }
+
+    /// The number given to the first numbered line. Defaults to 1.
+    pub fn start(mut self, start: i64) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// The step between consecutive line numbers. Defaults to 1.
+    pub fn increment(mut self, increment: i64) -> Self {
+        self.increment = increment;
+        self
+    }
+
+    /// The columns line numbers are padded to. Defaults to 6.
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// How line numbers are justified in their columns.
+    pub fn number_format(mut self, number_format: NumberFormat) -> Self {
+        self.number_format = number_format;
+        self
+    }
+
+    /// The text written between a line number and its line. Defaults to a
+    /// tab.
+    pub fn number_separator(mut self, number_separator: &str) -> Self {
+        self.number_separator = number_separator.to_string();
+        self
+    }
+
+    /// Indents lines without a number by the width of the number column,
+    /// as nl does, so all lines stay aligned.
+    pub fn pad_unnumbered(mut self, pad_unnumbered: bool) -> Self {
+        self.pad_unnumbered = pad_unnumbered;
+        self
+    }
+
+    /// Splits the input into logical pages at lines consisting of the
+    /// delimiter repeated three times (header), twice (body) or once
+    /// (footer), like nl. Delimiter lines are output as empty lines and
+    /// restart the numbering.
+    pub fn section_delimiter(mut self, delimiter: &str) -> Self {
+        self.section_delimiter = Some(delimiter.as_bytes().to_vec());
+        self
+    }
+
+    /// Numbers the header lines of each logical page.
+    pub fn header_numbering(mut self, header: Numbering) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Numbers the footer lines of each logical page.
+    pub fn footer_numbering(mut self, footer: Numbering) -> Self {
+        self.footer = footer;
+        self
+    }
+
+    /// Suppresses repeated empty lines.
+    pub fn squeeze_blank(mut self, squeeze_blank: bool) -> Self {
+        self.squeeze_blank = squeeze_blank;
+        self
+    }
+
+    /// Displays `$` at the end of each line.
+    pub fn show_ends(mut self, show_ends: bool) -> Self {
+        self.show_ends = show_ends;
+        self
+    }
+
+    /// Displays tabs as `^I`.
+    pub fn show_tabs(mut self, show_tabs: bool) -> Self {
+        self.show_tabs = show_tabs;
+        self
+    }
+
+    /// Displays control characters and bytes above 127 in `^` and `M-`
+    /// notation.
+    pub fn show_nonprinting(mut self, show_nonprinting: bool) -> Self {
+        self.show_nonprinting = show_nonprinting;
+        self
+    }
+
+    /// Copies everything in `input` to `output`, writing each line as soon
+    /// as it has been read.
+    pub fn copy<R: Read, W: Write>(&self, input: R, mut output: W) -> io::Result<()> {
+        let input = BufReader::new(input);
+        let mut state = LineState::default();
+        self.stream("-", input, &mut output, None, &mut state)
+            .and_then(|()| self.finish(&mut output, None, &mut state))
+            .map_err(io::Error::from)
+    }
+
+    /// Wraps `output` in a writer applying these options.
+    pub fn writer<W: Write>(&self, output: W) -> CatWriter<'_, W> {
+        CatWriter {
+            cat: self,
+            inner: output,
+            state: LineState::default(),
+        }
+    }
+
+    /// True when no option changes the input, so it can be copied byte for byte.
+    pub(crate) fn is_passthrough(&self) -> bool {
+        matches!(self.number, Numbering::None)
+            && self.section_delimiter.is_none()
+            && !(self.show_ends
+                || self.show_tabs
+                || self.show_nonprinting
+                || self.squeeze_blank
+                || self.selection.is_some())
+    }
+
+    /// Writes everything left in `file` to `out`, highlighting lines with
+    /// `syntax`, if any.
+    pub(crate) fn stream(
+        &self,
+        filename: &str,
+        mut file: impl BufRead,
+        out: &mut impl Write,
+        syntax: Option<&Syntax>,
+        state: &mut LineState,
+    ) -> Result<(), CatError> {
+        if self.is_passthrough() && syntax.is_none() {
+            return io::copy(&mut file, out)
+                .map(drop)
+                .map_err(|e| CatError::copy(filename, e));
+        }
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            buf.append(&mut state.pending);
+            file.read_until(b'\n', &mut buf)
+                .map_err(|e| CatError::input(filename, e))?;
+            // Lines are only matched and numbered once they are whole, so
+            // the start of one is held until the rest is read.
+            let Some(line) = buf.strip_suffix(b"\n") else {
+                state.pending.append(&mut buf);
+                break;
+            };
+            if self.cat_line(out, line, true, syntax, state)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the line left without a newline at the end of the input, if
+    /// any. Called once the input has ended, rather than merely paused.
+    pub(crate) fn finish(
+        &self,
+        out: &mut impl Write,
+        syntax: Option<&Syntax>,
+        state: &mut LineState,
+    ) -> Result<(), CatError> {
+        if state.pending.is_empty() {
+            return Ok(());
+        }
+        let line = std::mem::take(&mut state.pending);
+        self.cat_line(out, &line, false, syntax, state).map(drop)
+    }
+
+    /// Writes one line read from the input, or the section delimiter it is.
+    /// Returns whether the selection, if any, has been output in full.
+    fn cat_line(
+        &self,
+        out: &mut impl Write,
+        line: &[u8],
+        newline: bool,
+        syntax: Option<&Syntax>,
+        state: &mut LineState,
+    ) -> Result<bool, CatError> {
+        if let Some(section) = self.section_start(line) {
+            state.section = section;
+            state.lines_numbered = 0;
+            state.numbered_read = 0;
+            // Selected lines are picked by their place in the input,
+            // which delimiters don't have.
+            if self.selection.is_none() {
+                state.prev_blank = true;
+                self.write_line(out, b"", newline, None)?;
+            }
+            return Ok(false);
+        }
+        state.lines_read += 1;
+        let Some(selection) = &self.selection else {
+            self.show_line(out, line, newline, None, syntax, state)?;
+            return Ok(false);
+        };
+        // Selected lines keep the numbers they have in the input.
+        let number = match self.is_numbered(line, state.section) {
+            true => {
+                state.numbered_read += 1;
+                Some(state.numbered_read)
+            }
+            false => None,
+        };
+        let held = HeldLine {
+            line_num: state.lines_read,
+            number,
+            bytes: line.to_vec(),
+            newline,
+        };
+        for held in selection.push(&mut state.select, held) {
+            let number = held.number;
+            self.show_line(out, &held.bytes, held.newline, number, syntax, state)?;
+        }
+        Ok(selection.is_done(&state.select, state.lines_read))
+    }
+
+    /// The section a delimiter line starts, or `None` for other lines.
+    fn section_start(&self, line: &[u8]) -> Option<Section> {
+        let delimiter = self.section_delimiter.as_deref()?;
+        if delimiter.is_empty() || !line.len().is_multiple_of(delimiter.len()) {
+            return None;
+        }
+        let section = match line.len() / delimiter.len() {
+            3 => Section::Header,
+            2 => Section::Body,
+            1 => Section::Footer,
+            _ => return None,
+        };
+        line.chunks(delimiter.len())
+            .all(|chunk| chunk == delimiter)
+            .then_some(section)
+    }
+
+    /// Whether `line` gets a number under the style of `section`.
+    fn is_numbered(&self, line: &[u8], section: Section) -> bool {
+        let style = match section {
+            Section::Header => &self.header,
+            Section::Body => &self.number,
+            Section::Footer => &self.footer,
+        };
+        match style {
+            Numbering::None => false,
+            Numbering::All => true,
+            Numbering::NonBlank => !line.is_empty(),
+            Numbering::Matching(re) => re.is_match(line),
+        }
+    }
+
+    /// Writes one line, squeezing and numbering it. Lines are counted as
+    /// they are output unless a selection is in effect, in which case
+    /// `number` is the count to use.
+    fn show_line(
+        &self,
+        out: &mut impl Write,
+        line: &[u8],
+        newline: bool,
+        number: Option<usize>,
+        syntax: Option<&Syntax>,
+        state: &mut LineState,
+    ) -> Result<(), CatError> {
+        let blank = line.is_empty();
+        if self.squeeze_blank && blank && state.prev_blank {
+            return Ok(());
+        }
+        state.prev_blank = blank;
+        let number = match self.selection {
+            Some(_) => number,
+            None if self.is_numbered(line, state.section) => Some(state.lines_numbered + 1),
+            None => None,
+        };
+        match number {
+            Some(number) => {
+                state.lines_numbered = number;
+                self.write_number(out, number)?;
+            }
+            None if self.pad_unnumbered => {
+                let pad = self.width + self.number_separator.len();
+                write!(out, "{:pad$}", "")?;
+            }
+            None => {}
+        }
+        self.write_line(out, line, newline, syntax)?;
+        Ok(())
+    }
+
+    /// Writes the number of the `count`th numbered line and the separator.
+    fn write_number(&self, out: &mut impl Write, count: usize) -> io::Result<()> {
+        let number = self
+            .start
+            .saturating_add((count as i64 - 1).saturating_mul(self.increment));
+        let width = self.width;
+        let number = match self.number_format {
+            NumberFormat::Left => format!("{:<width$}", number),
+            NumberFormat::Right => format!("{:>width$}", number),
+            NumberFormat::RightZero => format!("{:0width$}", number),
+        };
+        if self.color {
+            write_styled(out, number.as_bytes(), Style::Gutter)?;
+        } else {
+            out.write_all(number.as_bytes())?;
+        }
+        out.write_all(self.number_separator.as_bytes())
+    }
+
+    fn write_line(
+        &self,
+        out: &mut impl Write,
+        line: &[u8],
+        newline: bool,
+        syntax: Option<&Syntax>,
+    ) -> io::Result<()> {
+        let mut pos = 0;
+        for (range, style) in syntax.map_or_else(Vec::new, |s| highlight::spans(s, line)) {
+            self.write_bytes(out, &line[pos..range.start])?;
+            let mut styled = vec![];
+            self.write_bytes(&mut styled, &line[range.clone()])?;
+            write_styled(out, &styled, style)?;
+            pos = range.end;
+        }
+        self.write_bytes(out, &line[pos..])?;
+        if newline {
+            if self.show_ends {
+                out.write_all(b"$")?;
+            }
+            out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    fn write_bytes(&self, out: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+        if self.show_tabs || self.show_nonprinting {
+            for &byte in bytes {
+                self.write_byte(out, byte)?;
+            }
+            Ok(())
+        } else {
+            out.write_all(bytes)
+        }
+    }
+
+    /// Writes a single byte using the `^X` and `M-X` notation of GNU cat for
+    /// whichever of tabs and nonprinting characters are being shown.
+    fn write_byte(&self, out: &mut impl Write, byte: u8) -> io::Result<()> {
+        if byte == b'\t' {
+            return if self.show_tabs {
+                out.write_all(b"^I")
+            } else {
+                out.write_all(b"\t")
+            };
+        }
+        if !self.show_nonprinting {
+            return out.write_all(&[byte]);
+        }
+        let byte = if byte >= 0x80 {
+            out.write_all(b"M-")?;
+            byte - 0x80
+        } else {
+            byte
+        };
+        match byte {
+            0x00..=0x1f => out.write_all(&[b'^', byte + 0x40]),
+            0x7f => out.write_all(b"^?"),
+            _ => out.write_all(&[byte]),
+        }
+    }
+}
+
+impl<W: Write> CatWriter<'_, W> {
+    /// Writes the last line, if it had no newline, and returns the wrapped
+    /// writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.cat
+            .finish(&mut self.inner, None, &mut self.state)
+            .map_err(io::Error::from)?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for CatWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.cat
+            .stream("-", buf, &mut self.inner, None, &mut self.state)
+            .map_err(io::Error::from)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn write_styled(out: &mut impl Write, bytes: &[u8], style: Style) -> io::Result<()> {
+    write!(out, "\x1b[{}m", style.code())?;
+    out.write_all(bytes)?;
+    out.write_all(b"\x1b[0m")
+}
@@ -0,0 +1,126 @@
+//! Copying input to output unchanged, letting the kernel move the bytes
+//! whenever it can.
+
+use std::io::{self, ErrorKind, Read, Write};
+
+/// Size of the buffer used when the kernel can't copy for us.
+pub const BUF_SIZE: usize = 128 * 1024;
+
+/// Copies everything left in `input` to `output` and returns the number of
+/// bytes written.
+///
+/// On Linux this tries `copy_file_range`, then `splice` (when either end is a
+/// pipe), then `sendfile`, before falling back to [`buffered_copy`]. Any data
+/// buffered in `output` must be flushed before calling this.
+#[cfg(target_os = "linux")]
+pub fn copy<R, W>(input: &mut R, output: &mut W) -> io::Result<u64>
+where
+    R: Read + std::os::fd::AsFd,
+    W: Write + std::os::fd::AsFd,
+{
+    use std::os::fd::AsRawFd;
+
+    let in_fd = input.as_fd().as_raw_fd();
+    let out_fd = output.as_fd().as_raw_fd();
+    let mut total = 0;
+    for method in [Method::CopyFileRange, Method::Splice, Method::Sendfile] {
+        if kernel_copy(method, in_fd, out_fd, &mut total)? {
+            return Ok(total);
+        }
+    }
+    total += buffered_copy(input, output)?;
+    Ok(total)
+}
+
+/// Copies everything left in `input` to `output` and returns the number of
+/// bytes written.
+#[cfg(not(target_os = "linux"))]
+pub fn copy<R: Read, W: Write>(input: &mut R, output: &mut W) -> io::Result<u64> {
+    buffered_copy(input, output)
+}
+
+/// Copies `input` to `output` through a single large buffer.
+pub fn buffered_copy<R: Read, W: Write>(input: &mut R, output: &mut W) -> io::Result<u64> {
+    let mut buf = vec![0; BUF_SIZE];
+    let mut total = 0;
+    loop {
+        let bytes_read = match input.read(&mut buf) {
+            Ok(0) => return Ok(total),
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        output.write_all(&buf[..bytes_read])?;
+        total += bytes_read as u64;
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Clone, Copy)]
+enum Method {
+    CopyFileRange,
+    Splice,
+    Sendfile,
+}
+
+/// Largest number of bytes requested from a single system call.
+#[cfg(target_os = "linux")]
+const CHUNK_SIZE: usize = 1 << 30;
+
+/// Runs `method` until the input is exhausted, adding the bytes copied to
+/// `total`. Returns `false` if the method can't be used for these two file
+/// descriptors, in which case the caller should try the next one.
+#[cfg(target_os = "linux")]
+fn kernel_copy(method: Method, in_fd: i32, out_fd: i32, total: &mut u64) -> io::Result<bool> {
+    let mut copied = 0;
+    loop {
+        // SAFETY: both descriptors stay open for the duration of the call and
+        // null offsets make the kernel use and update the file positions.
+        let ret = unsafe {
+            match method {
+                Method::CopyFileRange => libc::copy_file_range(
+                    in_fd,
+                    std::ptr::null_mut(),
+                    out_fd,
+                    std::ptr::null_mut(),
+                    CHUNK_SIZE,
+                    0,
+                ),
+                Method::Splice => libc::splice(
+                    in_fd,
+                    std::ptr::null_mut(),
+                    out_fd,
+                    std::ptr::null_mut(),
+                    CHUNK_SIZE,
+                    libc::SPLICE_F_MOVE,
+                ),
+                Method::Sendfile => libc::sendfile(out_fd, in_fd, std::ptr::null_mut(), CHUNK_SIZE),
+            }
+        };
+        match ret {
+            // Some pseudo filesystems report 0 from copy_file_range even when
+            // there is data, so only trust EOF once something has been copied.
+            0 => return Ok(copied > 0 || !matches!(method, Method::CopyFileRange)),
+            n if n > 0 => {
+                copied += n as u64;
+                *total += n as u64;
+            }
+            _ => {
+                let err = io::Error::last_os_error();
+                match err.raw_os_error() {
+                    Some(libc::EINTR) => continue,
+                    Some(
+                        libc::EINVAL
+                        | libc::ENOSYS
+                        | libc::EXDEV
+                        | libc::EOPNOTSUPP
+                        | libc::EBADF
+                        | libc::EPERM
+                        | libc::ESPIPE,
+                    ) => return Ok(false),
+                    _ => return Err(err),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Transparent decompression of input streams, recognised by magic bytes.
+
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+
+type Input = Box<dyn BufRead>;
+
+/// A compression format and how to decode it.
+struct Decoder {
+    magic: &'static [u8],
+    wrap: fn(Input) -> io::Result<Box<dyn Read>>,
+}
+
+/// Every supported format. Each decoder also reads streams made of several
+/// concatenated members, as produced by `cat a.gz b.gz`.
+const DECODERS: &[Decoder] = &[
+    Decoder {
+        magic: &[0x1f, 0x8b],
+        wrap: |r| Ok(Box::new(flate2::bufread::MultiGzDecoder::new(r))),
+    },
+    Decoder {
+        magic: &[0x28, 0xb5, 0x2f, 0xfd],
+        wrap: |r| Ok(Box::new(zstd::stream::read::Decoder::with_buffer(r)?)),
+    },
+    Decoder {
+        magic: &[0xfd, b'7', b'z', b'X', b'Z', 0x00],
+        wrap: |r| Ok(Box::new(xz2::bufread::XzDecoder::new_multi_decoder(r))),
+    },
+    Decoder {
+        magic: b"BZh",
+        wrap: |r| Ok(Box::new(bzip2::bufread::MultiBzDecoder::new(r))),
+    },
+];
+
+/// Wraps `input` in the decoder matching its leading bytes. Input in no
+/// known format is passed through unchanged.
+pub fn decode(mut input: Input) -> io::Result<Input> {
+    let magic_len = DECODERS.iter().map(|d| d.magic.len()).max().unwrap_or(0);
+    let mut header = Vec::with_capacity(magic_len);
+    (&mut input)
+        .take(magic_len as u64)
+        .read_to_end(&mut header)?;
+    let decoder = DECODERS.iter().find(|d| header.starts_with(d.magic));
+    let input: Input = Box::new(Cursor::new(header).chain(input));
+    match decoder {
+        Some(decoder) => Ok(Box::new(BufReader::new((decoder.wrap)(input)?))),
+        None => Ok(input),
+    }
+}
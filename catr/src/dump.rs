@@ -0,0 +1,130 @@
+//! Hex and octal dumps of the input, in the layouts of `hexdump -C` and
+//! `od -A o -t o1z`.
+
+use crate::CatError;
+use std::io::{self, Read, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Radix {
+    Hex,
+    Octal,
+}
+
+#[derive(Debug)]
+pub struct Dump {
+    pub radix: Radix,
+    /// Bytes shown on each line.
+    pub width: usize,
+    /// Bytes skipped at the start of each input.
+    pub skip: u64,
+    /// Most bytes shown from each input.
+    pub length: Option<u64>,
+}
+
+impl Dump {
+    /// Writes a dump of `file` to `out`. Offsets count from the start of the
+    /// input, including skipped bytes, and repeats of a line are shown as a
+    /// single `*`.
+    pub fn write(
+        &self,
+        filename: &str,
+        mut file: impl Read,
+        out: &mut impl Write,
+    ) -> Result<(), CatError> {
+        let read_err = |e| CatError::input(filename, e);
+        let mut offset =
+            io::copy(&mut (&mut file).take(self.skip), &mut io::sink()).map_err(read_err)?;
+        let mut file = file.take(self.length.unwrap_or(u64::MAX));
+        let mut line = vec![0; self.width];
+        let mut prev: Option<Vec<u8>> = None;
+        let mut squeezing = false;
+        loop {
+            let len = read_full(&mut file, &mut line).map_err(read_err)?;
+            if len == 0 {
+                break;
+            }
+            let bytes = &line[..len];
+            if prev.as_deref() == Some(bytes) {
+                if !squeezing {
+                    writeln!(out, "*")?;
+                    squeezing = true;
+                }
+            } else {
+                self.write_line(out, offset, bytes)?;
+                squeezing = false;
+                prev = Some(bytes.to_vec());
+            }
+            offset += len as u64;
+            if len < self.width {
+                break;
+            }
+        }
+        match self.radix {
+            Radix::Hex if offset == 0 => {}
+            Radix::Hex => writeln!(out, "{:08x}", offset)?,
+            Radix::Octal => writeln!(out, "{:07o}", offset)?,
+        }
+        Ok(())
+    }
+
+    fn write_line(&self, out: &mut impl Write, offset: u64, bytes: &[u8]) -> io::Result<()> {
+        match self.radix {
+            Radix::Hex => {
+                write!(out, "{:08x} ", offset)?;
+                for i in 0..self.width {
+                    if i % 8 == 0 {
+                        write!(out, " ")?;
+                    }
+                    match bytes.get(i) {
+                        Some(byte) => write!(out, "{:02x} ", byte)?,
+                        None => write!(out, "   ")?,
+                    }
+                }
+                write!(out, " |")?;
+                write_printable(out, bytes)?;
+                writeln!(out, "|")
+            }
+            Radix::Octal => {
+                write!(out, "{:07o}", offset)?;
+                for i in 0..self.width {
+                    match bytes.get(i) {
+                        Some(byte) => write!(out, " {:03o}", byte)?,
+                        None => write!(out, "    ")?,
+                    }
+                }
+                write!(out, "  >")?;
+                write_printable(out, bytes)?;
+                writeln!(out, "<")
+            }
+        }
+    }
+}
+
+/// Writes printable ASCII as is and everything else as `.`.
+fn write_printable(out: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    let text: Vec<u8> = bytes
+        .iter()
+        .map(|&b| {
+            if b == b' ' || b.is_ascii_graphic() {
+                b
+            } else {
+                b'.'
+            }
+        })
+        .collect();
+    out.write_all(&text)
+}
+
+/// Reads until `buf` is full or the input ends, returning the bytes read.
+fn read_full(file: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match file.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(len)
+}
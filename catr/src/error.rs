@@ -0,0 +1,94 @@
+//! Errors reported while concatenating files.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, ErrorKind};
+
+#[derive(Debug)]
+pub enum CatError {
+    /// Opening or reading an input failed. Only that file is skipped.
+    Input { filename: String, source: io::Error },
+    /// An input named a directory.
+    IsDirectory { filename: String },
+    /// Writing to standard output failed, which ends the run.
+    Output(io::Error),
+}
+
+impl CatError {
+    pub fn input(filename: &str, source: io::Error) -> Self {
+        CatError::Input {
+            filename: filename.to_string(),
+            source,
+        }
+    }
+
+    /// Classifies an error from copying `filename` to standard output, where
+    /// the failing side can only be told apart by the kind of error.
+    pub fn copy(filename: &str, source: io::Error) -> Self {
+        match source.kind() {
+            ErrorKind::BrokenPipe
+            | ErrorKind::WriteZero
+            | ErrorKind::StorageFull
+            | ErrorKind::FileTooLarge
+            | ErrorKind::QuotaExceeded => CatError::Output(source),
+            _ => CatError::input(filename, source),
+        }
+    }
+
+    /// True if standard output was closed by the reader, as when piping to
+    /// `head`, which ends the run quietly.
+    pub fn is_broken_pipe(&self) -> bool {
+        matches!(self, CatError::Output(e) if e.kind() == ErrorKind::BrokenPipe)
+    }
+}
+
+/// I/O errors not labelled as coming from an input are from writing output.
+impl From<io::Error> for CatError {
+    fn from(source: io::Error) -> Self {
+        CatError::Output(source)
+    }
+}
+
+/// Unwraps the underlying I/O error, for callers of the [`crate::Cat`] API.
+impl From<CatError> for io::Error {
+    fn from(err: CatError) -> Self {
+        match err {
+            CatError::Input { source, .. } | CatError::Output(source) => source,
+            CatError::IsDirectory { .. } => io::Error::from(ErrorKind::IsADirectory),
+        }
+    }
+}
+
+impl fmt::Display for CatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CatError::Input { filename, source } => {
+                write!(f, "{}: {}", filename, strerror(source))
+            }
+            CatError::IsDirectory { filename } => write!(f, "{}: Is a directory", filename),
+            CatError::Output(source) => write!(f, "write error: {}", strerror(source)),
+        }
+    }
+}
+
+impl Error for CatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CatError::Input { source, .. } | CatError::Output(source) => Some(source),
+            CatError::IsDirectory { .. } => None,
+        }
+    }
+}
+
+/// The system's description of `err` without the " (os error N)" suffix,
+/// matching the messages of the GNU tools.
+fn strerror(err: &io::Error) -> String {
+    let message = err.to_string();
+    match err.raw_os_error() {
+        Some(code) => message
+            .strip_suffix(&format!(" (os error {})", code))
+            .unwrap_or(&message)
+            .to_string(),
+        None => message,
+    }
+}
@@ -0,0 +1,99 @@
+//! Following a file as it grows, like `tail -f`.
+
+use crate::cat::LineState;
+use crate::highlight::Syntax;
+use crate::{Cat, CatError};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs::{self, File, Metadata};
+use std::io::{BufReader, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+/// How often the file is checked when no change notification arrives.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Writes `file` to `out` and then keeps writing whatever is appended to
+/// `path`. Truncation restarts from the beginning of the file, and when
+/// `path` is replaced (as log rotation does) the rest of the old file is
+/// written before switching to the new one. Never returns unless an error
+/// occurs.
+pub fn follow(
+    path: &str,
+    mut file: File,
+    out: &mut impl Write,
+    cat: &Cat,
+    syntax: Option<&Syntax>,
+) -> Result<(), CatError> {
+    let mut state = LineState::default();
+    let (tx, rx) = mpsc::channel();
+    let watcher = watch(path, move || {
+        let _ = tx.send(());
+    });
+    loop {
+        cat.stream(path, BufReader::new(&mut file), out, syntax, &mut state)?;
+        out.flush()?;
+        wait(watcher.as_ref(), &rx);
+
+        let current = file.metadata().map_err(|e| CatError::input(path, e))?;
+        let position = file
+            .stream_position()
+            .map_err(|e| CatError::input(path, e))?;
+        match fs::metadata(path) {
+            Ok(meta) if !is_same_file(&meta, &current) => {
+                cat.stream(path, BufReader::new(&mut file), out, syntax, &mut state)?;
+                cat.finish(out, syntax, &mut state)?;
+                match File::open(path) {
+                    Ok(new_file) => {
+                        eprintln!("catr: {}: file replaced, following new file", path);
+                        file = new_file;
+                    }
+                    // Gone again before it could be opened; retry next time.
+                    Err(_) => continue,
+                }
+            }
+            _ if current.len() < position => {
+                eprintln!("catr: {}: file truncated", path);
+                file.seek(SeekFrom::Start(0))
+                    .map_err(|e| CatError::input(path, e))?;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Watches the directory holding `path` with inotify (or the platform's
+/// equivalent), so renames and new files are seen as well as writes.
+/// Returns `None` when no watcher is available and polling has to do.
+fn watch(path: &str, notify: impl Fn() + Send + 'static) -> Option<RecommendedWatcher> {
+    let dir = match Path::new(path).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut watcher = notify::recommended_watcher(move |_| notify()).ok()?;
+    watcher.watch(dir, RecursiveMode::NonRecursive).ok()?;
+    Some(watcher)
+}
+
+/// Blocks until the watcher reports a change or the poll interval passes.
+fn wait(watcher: Option<&RecommendedWatcher>, rx: &Receiver<()>) {
+    if watcher.is_some() {
+        let _ = rx.recv_timeout(POLL_INTERVAL);
+        // One change usually arrives as a burst of events.
+        while rx.try_recv().is_ok() {}
+    } else {
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(unix)]
+fn is_same_file(a: &Metadata, b: &Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+fn is_same_file(_: &Metadata, _: &Metadata) -> bool {
+    true
+}
@@ -0,0 +1,285 @@
+//! Line-based syntax highlighting for a handful of common formats.
+//!
+//! Each line is tokenized on its own, so constructs spanning several lines
+//! (block comments, multi-line strings) are only colored line by line.
+
+use std::ops::Range;
+use std::path::Path;
+
+/// What a highlighted piece of a line is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Style {
+    Keyword,
+    String,
+    Number,
+    Constant,
+    Comment,
+    Key,
+    Heading,
+    Variable,
+    Added,
+    Removed,
+    Gutter,
+}
+
+impl Style {
+    /// The SGR parameters used for this style.
+    pub fn code(self) -> &'static str {
+        match self {
+            Style::Keyword => "35",
+            Style::String => "32",
+            Style::Number => "36",
+            Style::Constant => "33",
+            Style::Comment => "2",
+            Style::Key => "34",
+            Style::Heading => "1;34",
+            Style::Variable => "36",
+            Style::Added => "32",
+            Style::Removed => "31",
+            Style::Gutter => "2",
+        }
+    }
+}
+
+/// The lexical rules of one language.
+#[derive(Debug)]
+pub struct Syntax {
+    name: &'static str,
+    extensions: &'static [&'static str],
+    line_comment: Option<&'static [u8]>,
+    quotes: &'static [u8],
+    keywords: &'static [&'static str],
+    constants: &'static [&'static str],
+    /// Separator after which a bare word or string is colored as a key.
+    key_separator: Option<u8>,
+    /// Lines starting with `[` are table headings.
+    headings: bool,
+    /// `$name` and `${name}` are variables.
+    variables: bool,
+    /// `name!` is a macro and `#[...]` an attribute.
+    macros: bool,
+    /// Lines are colored whole by their first characters.
+    diff: bool,
+}
+
+const PLAIN: Syntax = Syntax {
+    name: "",
+    extensions: &[],
+    line_comment: None,
+    quotes: &[],
+    keywords: &[],
+    constants: &[],
+    key_separator: None,
+    headings: false,
+    variables: false,
+    macros: false,
+    diff: false,
+};
+
+/// Every supported language.
+const SYNTAXES: &[Syntax] = &[
+    Syntax {
+        name: "json",
+        extensions: &["json"],
+        quotes: b"\"",
+        constants: &["true", "false", "null"],
+        key_separator: Some(b':'),
+        ..PLAIN
+    },
+    Syntax {
+        name: "toml",
+        extensions: &["toml"],
+        line_comment: Some(b"#"),
+        quotes: b"\"'",
+        constants: &["true", "false"],
+        key_separator: Some(b'='),
+        headings: true,
+        ..PLAIN
+    },
+    Syntax {
+        name: "yaml",
+        extensions: &["yaml", "yml"],
+        line_comment: Some(b"#"),
+        quotes: b"\"'",
+        constants: &["true", "false", "null", "yes", "no", "on", "off"],
+        key_separator: Some(b':'),
+        ..PLAIN
+    },
+    Syntax {
+        name: "rust",
+        extensions: &["rs"],
+        line_comment: Some(b"//"),
+        quotes: b"\"",
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+            "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+            "type", "unsafe", "use", "where", "while",
+        ],
+        constants: &["true", "false", "None", "Some", "Ok", "Err"],
+        macros: true,
+        ..PLAIN
+    },
+    Syntax {
+        name: "sh",
+        extensions: &["sh", "bash", "zsh"],
+        line_comment: Some(b"#"),
+        quotes: b"\"'",
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+            "in", "local", "readonly", "return", "select", "then", "until", "while",
+        ],
+        constants: &["true", "false"],
+        variables: true,
+        ..PLAIN
+    },
+    Syntax {
+        name: "diff",
+        extensions: &["diff", "patch"],
+        diff: true,
+        ..PLAIN
+    },
+];
+
+/// The names accepted by `--language`.
+pub fn names() -> Vec<&'static str> {
+    SYNTAXES.iter().map(|s| s.name).collect()
+}
+
+/// Picks the syntax named `language`, or else the one matching the extension
+/// of `filename`, looking through a compression suffix like `.gz`.
+pub fn detect(filename: &str, language: Option<&str>) -> Option<&'static Syntax> {
+    if let Some(language) = language {
+        return SYNTAXES.iter().find(|s| s.name == language);
+    }
+    let mut path = Path::new(filename);
+    if let Some("gz" | "zst" | "xz" | "bz2") = path.extension().and_then(|e| e.to_str()) {
+        path = Path::new(path.file_stem()?);
+    }
+    let ext = path.extension()?.to_str()?;
+    SYNTAXES.iter().find(|s| s.extensions.contains(&ext))
+}
+
+/// Splits `line` into the ranges to color. Bytes outside every range are
+/// written plain.
+pub fn spans(syntax: &Syntax, line: &[u8]) -> Vec<(Range<usize>, Style)> {
+    if syntax.diff {
+        return diff_spans(line);
+    }
+    if syntax.headings && line.trim_ascii_start().starts_with(b"[") {
+        return vec![(0..line.len(), Style::Heading)];
+    }
+    let mut spans = vec![];
+    let mut i = 0;
+    while i < line.len() {
+        let byte = line[i];
+        let at_word_start = i == 0 || !is_word(line[i - 1]);
+        let start = i;
+        if let Some(comment) = syntax.line_comment {
+            if line[i..].starts_with(comment) && (i == 0 || line[i - 1].is_ascii_whitespace()) {
+                spans.push((i..line.len(), Style::Comment));
+                break;
+            }
+        }
+        if syntax.quotes.contains(&byte) {
+            i = end_of_string(line, i);
+            spans.push((start..i, key_or(syntax, line, i, Style::String)));
+        } else if byte.is_ascii_digit() && at_word_start {
+            while i < line.len() && (is_word(line[i]) || line[i] == b'.') {
+                i += 1;
+            }
+            spans.push((start..i, Style::Number));
+        } else if is_word(byte) && at_word_start {
+            while i < line.len()
+                && (is_word(line[i]) || (syntax.key_separator.is_some() && line[i] == b'-'))
+            {
+                i += 1;
+            }
+            let word = &line[start..i];
+            if syntax.macros && line.get(i) == Some(&b'!') {
+                i += 1;
+                spans.push((start..i, Style::Variable));
+            } else if syntax.keywords.iter().any(|k| k.as_bytes() == word) {
+                spans.push((start..i, Style::Keyword));
+            } else if syntax.constants.iter().any(|k| k.as_bytes() == word) {
+                spans.push((start..i, key_or(syntax, line, i, Style::Constant)));
+            } else if key_or(syntax, line, i, Style::String) == Style::Key {
+                spans.push((start..i, Style::Key));
+            }
+        } else if syntax.variables && byte == b'$' {
+            i = end_of_variable(line, i + 1);
+            spans.push((start..i, Style::Variable));
+        } else if syntax.macros && (line[i..].starts_with(b"#[") || line[i..].starts_with(b"#![")) {
+            i = line[i..]
+                .iter()
+                .position(|&b| b == b']')
+                .map_or(line.len(), |p| i + p + 1);
+            spans.push((start..i, Style::Comment));
+        } else {
+            i += 1;
+        }
+    }
+    spans
+}
+
+fn diff_spans(line: &[u8]) -> Vec<(Range<usize>, Style)> {
+    let style = if line.starts_with(b"+++")
+        || line.starts_with(b"---")
+        || line.starts_with(b"diff ")
+        || line.starts_with(b"index ")
+    {
+        Style::Heading
+    } else if line.starts_with(b"@@") {
+        Style::Number
+    } else if line.starts_with(b"+") {
+        Style::Added
+    } else if line.starts_with(b"-") {
+        Style::Removed
+    } else {
+        return vec![];
+    };
+    vec![(0..line.len(), style)]
+}
+
+fn is_word(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+/// `Style::Key` if the token ending at `end` is followed by the key
+/// separator, otherwise `style`.
+fn key_or(syntax: &Syntax, line: &[u8], end: usize, style: Style) -> Style {
+    let rest = line[end..].trim_ascii_start();
+    match syntax.key_separator {
+        Some(sep) if rest.first() == Some(&sep) => Style::Key,
+        _ => style,
+    }
+}
+
+/// The index just past the string starting with the quote at `start`.
+fn end_of_string(line: &[u8], start: usize) -> usize {
+    let quote = line[start];
+    let mut i = start + 1;
+    while i < line.len() {
+        match line[i] {
+            b'\\' if quote == b'"' => i += 2,
+            b if b == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    line.len()
+}
+
+/// The index just past a variable name starting at `start`, after the `$`.
+fn end_of_variable(line: &[u8], start: usize) -> usize {
+    match line.get(start) {
+        Some(b'{') => line[start..]
+            .iter()
+            .position(|&b| b == b'}')
+            .map_or(line.len(), |p| start + p + 1),
+        Some(b) if b"@*#?$!-0123456789".contains(b) => start + 1,
+        _ => {
+            let len = line[start..].iter().take_while(|&&b| is_word(b)).count();
+            start + len
+        }
+    }
+}
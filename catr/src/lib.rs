@@ -1,44 +1,171 @@
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches};
+use dump::{Dump, Radix};
+use encoding_rs::Encoding;
+use reverse::Separator;
+use select::Selection;
 use std::error::Error;
 use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, BufWriter, Cursor, IsTerminal, Write};
+use std::process::ExitCode;
+
+mod cat;
+pub mod copy;
+mod decode;
+mod dump;
+mod error;
+mod follow;
+mod highlight;
+mod prefetch;
+mod reverse;
+mod select;
+mod transcode;
+
+pub use cat::{Cat, CatWriter, NumberFormat, Numbering};
+pub use error::CatError;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
 
 #[derive(Debug)]
 pub struct Config {
     files: Vec<String>,
-    number_lines: bool,
-    number_nonblank_lines: bool,
+    cat: Cat,
+    decompress: bool,
+    follow: bool,
+    language: Option<String>,
+    dump: Option<Dump>,
+    reverse: Option<Separator>,
+    from_encoding: Option<&'static Encoding>,
+    to_encoding: Option<&'static Encoding>,
+    strip_bom: bool,
+    /// Threads reading files ahead, if any.
+    jobs: Option<usize>,
 }
 
-pub fn run(config: Config) -> MyResult<()> {
-    for filename in config.files {
-        match open(&filename) {
-            Err(e) => eprintln!("Faild to open {}: {}", filename, e),
-            Ok(file) => {
-                let mut line_num = 1;
-                for lines_iter in file.lines() {
-                    let line = lines_iter?;
-                    if config.number_lines {
-                        println!("{:>6}\t{}", line_num, line);
-                        line_num += 1;
-                    } else if config.number_nonblank_lines {
-                        if line.is_empty() {
-                            println!("");
-                        } else {
-                            println!("{:>6}\t{}", line_num, line);
-                            line_num += 1;
-                        }
-                    } else {
-                        println!("{}", line);
-                        line_num += 1;
-                    }
-                }
+impl Config {
+    /// True when the bytes read aren't the bytes of the file.
+    fn transforms_input(&self) -> bool {
+        self.decompress || self.from_encoding.is_some() || self.strip_bom
+    }
+}
+
+/// Writes every input to stdout. A file that can't be read is reported and
+/// skipped, and makes the exit code a failure.
+pub fn run(config: Config) -> MyResult<ExitCode> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    let mut status = ExitCode::SUCCESS;
+    let is_followed = |file_num| config.follow && file_num + 1 == config.files.len();
+    // Standard input and a followed file are read as the output gets to
+    // them, and everything else may be read ahead.
+    let read_ahead = |file_num, filename: &str| filename != "-" && !is_followed(file_num);
+    let mut prefetch = config.jobs.map(|jobs| {
+        let files = config.files.iter().enumerate();
+        let files = files.filter(|&(num, name)| read_ahead(num, name));
+        prefetch::Prefetch::new(files.map(|(_, name)| name.clone()).collect(), jobs)
+    });
+    for (file_num, filename) in config.files.iter().enumerate() {
+        let follow = filename != "-" && is_followed(file_num);
+        let fetched = match &mut prefetch {
+            Some(prefetch) if read_ahead(file_num, filename) => prefetch.next(),
+            _ => None,
+        };
+        match cat(filename, &mut out, &config, follow, fetched) {
+            Ok(()) => {}
+            Err(e) if e.is_broken_pipe() => return Ok(status),
+            Err(e @ CatError::Output(_)) => return Err(e.into()),
+            Err(e) => {
+                eprintln!("catr: {}", e);
+                status = ExitCode::FAILURE;
             }
         }
     }
-    Ok(())
+    match out.flush() {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(CatError::Output(e).into()),
+        _ => Ok(status),
+    }
+}
+
+/// Writes one input to `out`, following it afterwards if `follow` is set.
+/// `fetched` is the input if it has already been opened.
+fn cat(
+    filename: &str,
+    out: &mut BufWriter<io::StdoutLock>,
+    config: &Config,
+    follow: bool,
+    fetched: Option<Result<Source, CatError>>,
+) -> Result<(), CatError> {
+    let syntax = match config.cat.color {
+        true => highlight::detect(filename, config.language.as_deref()),
+        false => None,
+    };
+    if follow {
+        let file = open_file(filename)?;
+        return follow::follow(filename, file, out, &config.cat, syntax);
+    }
+    let source = match fetched {
+        Some(source) => source?,
+        None => Source::open(filename)?,
+    };
+    if config.cat.is_passthrough()
+        && syntax.is_none()
+        && config.dump.is_none()
+        && config.reverse.is_none()
+        && !config.transforms_input()
+    {
+        // Nothing is buffered in this mode, so the kernel can write to
+        // stdout directly.
+        out.flush()?;
+        let stdout = out.get_mut();
+        let copied = match source {
+            Source::Stdin => copy::copy(&mut io::stdin().lock(), stdout),
+            Source::File(mut file) => copy::copy(&mut file, stdout),
+            Source::Contents(contents) => stdout.write_all(&contents).map(|()| 0),
+        };
+        return copied.map(drop).map_err(|e| CatError::copy(filename, e));
+    }
+    match config.to_encoding {
+        Some(encoding) => {
+            let mut out = transcode::Encode::new(&mut *out, encoding);
+            write_input(filename, source, &mut out, config, syntax)?;
+            Ok(out.finish()?)
+        }
+        None => write_input(filename, source, out, config, syntax),
+    }
+}
+
+/// Writes one input to `out` as a dump, reversed or through the line
+/// transforms.
+fn write_input(
+    filename: &str,
+    source: Source,
+    out: &mut impl Write,
+    config: &Config,
+    syntax: Option<&highlight::Syntax>,
+) -> Result<(), CatError> {
+    if let Some(dump) = &config.dump {
+        return dump.write(filename, open(filename, source, config)?, out);
+    }
+    if let Some(sep) = &config.reverse {
+        // The other options apply to the lines as they come out reversed.
+        let mut out = config.cat.writer(&mut *out);
+        match source {
+            Source::File(file)
+                if !config.transforms_input()
+                    && file.metadata().is_ok_and(|meta| meta.is_file()) =>
+            {
+                reverse::reverse_file(filename, file, sep, &mut out)?
+            }
+            source => {
+                let file = open(filename, source, config)?;
+                reverse::reverse_stream(filename, file, sep, &mut out)?
+            }
+        };
+        out.into_inner()?;
+        return Ok(());
+    }
+    let file = open(filename, source, config)?;
+    let mut state = cat::LineState::default();
+    config.cat.stream(filename, file, out, syntax, &mut state)?;
+    config.cat.finish(out, syntax, &mut state)
 }
 
 pub fn get_args() -> MyResult<Config> {
@@ -69,17 +196,423 @@ pub fn get_args() -> MyResult<Config> {
                 .takes_value(false)
                 .conflicts_with("number_lines"),
         )
+        .arg(
+            Arg::with_name("start")
+                .long("start")
+                .value_name("NUMBER")
+                .help("Number the first line NUMBER")
+                .allow_hyphen_values(true),
+        )
+        .arg(
+            Arg::with_name("increment")
+                .long("increment")
+                .value_name("NUMBER")
+                .help("Add NUMBER to the line number at each numbered line")
+                .allow_hyphen_values(true),
+        )
+        .arg(
+            Arg::with_name("number_format")
+                .long("number-format")
+                .value_name("FORMAT")
+                .help("Justify line numbers left (ln), right (rn) or right with zeros (rz)")
+                .possible_values(&["ln", "rn", "rz"]),
+        )
+        .arg(
+            Arg::with_name("number_separator")
+                .long("number-separator")
+                .value_name("STRING")
+                .help("Write STRING after line numbers instead of a tab"),
+        )
+        .arg(
+            Arg::with_name("section_delimiter")
+                .long("section-delimiter")
+                .value_name("CC")
+                .help("Use CC in the lines splitting logical pages, as nl does [default: \\:]"),
+        )
+        .arg(
+            Arg::with_name("header_numbering")
+                .long("header-numbering")
+                .value_name("STYLE")
+                .help("Number header lines in STYLE: a (all), t (nonempty), n (none) or pREGEX"),
+        )
+        .arg(
+            Arg::with_name("body_numbering")
+                .long("body-numbering")
+                .value_name("STYLE")
+                .help("Number body lines in STYLE, indenting the rest like nl")
+                .conflicts_with_all(&["number_lines", "number_nonblank_lines"]),
+        )
+        .arg(
+            Arg::with_name("footer_numbering")
+                .long("footer-numbering")
+                .value_name("STYLE")
+                .help("Number footer lines in STYLE"),
+        )
+        .arg(
+            Arg::with_name("jobs")
+                .short("j")
+                .long("jobs")
+                .value_name("N")
+                .help("Read up to N files ahead in parallel, still writing them in order"),
+        )
+        .arg(
+            Arg::with_name("show_all")
+                .short("A")
+                .long("show-all")
+                .help("Equivalent to -vET")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("e")
+                .short("e")
+                .help("Equivalent to -vE")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("show_ends")
+                .short("E")
+                .long("show-ends")
+                .help("Display $ at end of each line")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("squeeze_blank")
+                .short("s")
+                .long("squeeze-blank")
+                .help("Suppress repeated empty output lines")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("t")
+                .short("t")
+                .help("Equivalent to -vT")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("show_tabs")
+                .short("T")
+                .long("show-tabs")
+                .help("Display TAB characters as ^I")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("show_nonprinting")
+                .short("v")
+                .long("show-nonprinting")
+                .help("Use ^ and M- notation, except for LFD and TAB")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("decompress")
+                .short("z")
+                .long("decompress")
+                .help("Decompress gzip, zstd, xz and bzip2 input, detected by magic bytes")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("follow")
+                .short("f")
+                .long("follow")
+                .help("Output appended data as the last file grows, across truncation and rotation")
+                .takes_value(false)
+                .conflicts_with("decompress"),
+        )
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .value_name("WHEN")
+                .help("Highlight syntax and dim line numbers")
+                .possible_values(&["auto", "always", "never"])
+                .default_value("auto"),
+        )
+        .arg(
+            Arg::with_name("language")
+                .long("language")
+                .value_name("LANG")
+                .help("Highlight as LANG instead of guessing from the file extension")
+                .possible_values(&highlight::names()),
+        )
+        .arg(
+            Arg::with_name("lines")
+                .long("lines")
+                .value_name("RANGES")
+                .help("Output only these lines, e.g. 500-800 or 1-10,20-"),
+        )
+        .arg(
+            Arg::with_name("from")
+                .long("from")
+                .value_name("REGEX")
+                .help("Start output at the first line matching REGEX"),
+        )
+        .arg(
+            Arg::with_name("to")
+                .long("to")
+                .value_name("REGEX")
+                .help("End output at the next line matching REGEX"),
+        )
+        .arg(
+            Arg::with_name("context")
+                .short("C")
+                .long("context")
+                .value_name("NUM")
+                .help("Also output NUM lines around each selected range"),
+        )
+        .arg(
+            Arg::with_name("hex")
+                .long("hex")
+                .help("Display a canonical hex+ASCII dump, like hexdump -C")
+                .takes_value(false)
+                .conflicts_with_all(&["octal", "follow"]),
+        )
+        .arg(
+            Arg::with_name("octal")
+                .long("octal")
+                .help("Display an octal+ASCII dump, like od -A o -t o1z")
+                .takes_value(false)
+                .conflicts_with("follow"),
+        )
+        .arg(
+            Arg::with_name("width")
+                .long("width")
+                .value_name("NUM")
+                .help("Line number columns [default: 6], or dump bytes per line [default: 16]"),
+        )
+        .arg(
+            Arg::with_name("skip")
+                .long("skip")
+                .value_name("BYTES")
+                .help("Skip BYTES at the start of each input of a dump"),
+        )
+        .arg(
+            Arg::with_name("length")
+                .long("length")
+                .value_name("BYTES")
+                .help("Dump at most BYTES of each input"),
+        )
+        .arg(
+            Arg::with_name("reverse")
+                .long("reverse")
+                .help("Output the records of each input last to first, like tac")
+                .takes_value(false)
+                .conflicts_with_all(&["follow", "hex", "octal"]),
+        )
+        .arg(
+            Arg::with_name("from_encoding")
+                .long("from-encoding")
+                .value_name("ENCODING")
+                .help("Convert input from ENCODING, unless a byte order mark says otherwise")
+                .conflicts_with("follow"),
+        )
+        .arg(
+            Arg::with_name("to_encoding")
+                .long("to-encoding")
+                .value_name("ENCODING")
+                .help("Convert output to ENCODING")
+                .conflicts_with("follow"),
+        )
+        .arg(
+            Arg::with_name("strip_bom")
+                .long("strip-bom")
+                .help("Remove a UTF-8 byte order mark from the start of each input")
+                .takes_value(false)
+                .conflicts_with("follow"),
+        )
+        .arg(
+            Arg::with_name("separator")
+                .long("separator")
+                .value_name("STRING")
+                .help("End records with STRING instead of newline when reversing")
+                .requires("reverse"),
+        )
+        .arg(
+            Arg::with_name("regex")
+                .long("regex")
+                .help("Interpret the separator as a regular expression")
+                .takes_value(false)
+                .requires("separator"),
+        )
         .get_matches();
+    let radix = if matches.is_present("hex") {
+        Some(Radix::Hex)
+    } else if matches.is_present("octal") {
+        Some(Radix::Octal)
+    } else {
+        None
+    };
+    let width = match parse_num(&matches, "width")? {
+        Some(0) => return Err("invalid width: \"0\"".into()),
+        width => width,
+    };
+    let dump = match radix {
+        Some(radix) => Some(Dump {
+            radix,
+            width: width.unwrap_or(16),
+            skip: parse_num(&matches, "skip")?.unwrap_or(0),
+            length: parse_num(&matches, "length")?,
+        }),
+        None => None,
+    };
+    let reverse = match matches.is_present("reverse") {
+        true => Some(Separator::new(
+            matches.value_of("separator").unwrap_or("\n"),
+            matches.is_present("regex"),
+        )?),
+        false => None,
+    };
+    let to_encoding = match matches.value_of("to_encoding") {
+        Some(label) => Some(transcode::encoding(label)?),
+        None => None,
+    };
+    let from_encoding = match matches.value_of("from_encoding") {
+        Some(label) => Some(transcode::encoding(label)?),
+        // Output is converted from UTF-8, or whatever a BOM says.
+        None if to_encoding.is_some() => Some(encoding_rs::UTF_8),
+        None => None,
+    };
+    let show_all = matches.is_present("show_all");
+    let number = if matches.is_present("number_lines") {
+        Numbering::All
+    } else if matches.is_present("number_nonblank_lines") {
+        Numbering::NonBlank
+    } else {
+        parse_numbering(&matches, "body_numbering")?.unwrap_or_default()
+    };
+    let mut cat = Cat::new()
+        .number(number)
+        .start(parse_num(&matches, "start")?.unwrap_or(1))
+        .increment(parse_num(&matches, "increment")?.unwrap_or(1))
+        .width(width.unwrap_or(6))
+        .number_format(match matches.value_of("number_format") {
+            Some("ln") => NumberFormat::Left,
+            Some("rz") => NumberFormat::RightZero,
+            _ => NumberFormat::Right,
+        })
+        .number_separator(matches.value_of("number_separator").unwrap_or("\t"))
+        .show_ends(show_all || matches.is_present("e") || matches.is_present("show_ends"))
+        .show_tabs(show_all || matches.is_present("t") || matches.is_present("show_tabs"))
+        .show_nonprinting(
+            show_all
+                || matches.is_present("e")
+                || matches.is_present("t")
+                || matches.is_present("show_nonprinting"),
+        )
+        .squeeze_blank(matches.is_present("squeeze_blank"));
+    let styles = ["header_numbering", "body_numbering", "footer_numbering"];
+    if matches.is_present("section_delimiter") || styles.iter().any(|s| matches.is_present(s)) {
+        // A single character delimiter is completed with a colon, as in nl.
+        let delimiter = match matches.value_of("section_delimiter").unwrap_or("\\:") {
+            d if d.chars().count() == 1 => format!("{}:", d),
+            d => d.to_string(),
+        };
+        cat = cat
+            .section_delimiter(&delimiter)
+            .header_numbering(parse_numbering(&matches, "header_numbering")?.unwrap_or_default())
+            .footer_numbering(parse_numbering(&matches, "footer_numbering")?.unwrap_or_default())
+            .pad_unnumbered(styles.iter().any(|s| matches.is_present(s)));
+    }
+    cat.color = match matches.value_of("color") {
+        Some("always") => true,
+        Some("auto") => io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        _ => false,
+    };
+    cat.selection = Selection::new(
+        matches.value_of("lines"),
+        matches.value_of("from"),
+        matches.value_of("to"),
+        parse_num(&matches, "context")?.unwrap_or(0),
+    )?;
     Ok(Config {
         files: matches.values_of_lossy("file").unwrap(),
-        number_lines: matches.is_present("number_lines"),
-        number_nonblank_lines: matches.is_present("number_nonblank_lines"),
+        cat,
+        decompress: matches.is_present("decompress"),
+        follow: matches.is_present("follow"),
+        language: matches.value_of("language").map(String::from),
+        dump,
+        reverse,
+        from_encoding,
+        to_encoding,
+        strip_bom: matches.is_present("strip_bom"),
+        jobs: match parse_num(&matches, "jobs")? {
+            Some(0) => return Err("invalid jobs: \"0\"".into()),
+            jobs => jobs,
+        },
     })
 }
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+/// Parses the value of the option `name` as a count.
+fn parse_num<T: std::str::FromStr>(matches: &ArgMatches, name: &str) -> MyResult<Option<T>> {
+    matches
+        .value_of(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format!("invalid {}: \"{}\"", name, value).into())
+        })
+        .transpose()
+}
+
+/// Parses the value of the option `name` as an nl numbering style.
+fn parse_numbering(matches: &ArgMatches, name: &str) -> MyResult<Option<Numbering>> {
+    let Some(style) = matches.value_of(name) else {
+        return Ok(None);
+    };
+    let numbering = match style {
+        "a" => Numbering::All,
+        "t" => Numbering::NonBlank,
+        "n" => Numbering::None,
+        _ => match style.strip_prefix('p') {
+            Some(re) => Numbering::Matching(regex::bytes::Regex::new(re)?),
+            None => return Err(format!("invalid numbering style: \"{}\"", style).into()),
+        },
+    };
+    Ok(Some(numbering))
+}
+
+/// An opened input.
+enum Source {
+    Stdin,
+    File(File),
+    /// The whole of a file, read ahead.
+    Contents(Vec<u8>),
+}
+
+impl Source {
+    fn open(filename: &str) -> Result<Self, CatError> {
+        match filename {
+            "-" => Ok(Source::Stdin),
+            _ => open_file(filename).map(Source::File),
+        }
+    }
+}
+
+/// Reads an input, decompressing it and converting it to UTF-8 as the
+/// options ask.
+fn open(filename: &str, source: Source, config: &Config) -> Result<Box<dyn BufRead>, CatError> {
+    let read_err = |e| CatError::input(filename, e);
+    let mut file: Box<dyn BufRead> = match source {
+        Source::Stdin => Box::new(BufReader::new(io::stdin())),
+        Source::File(file) => Box::new(BufReader::new(file)),
+        Source::Contents(contents) => Box::new(Cursor::new(contents)),
+    };
+    if config.decompress {
+        file = decode::decode(file).map_err(read_err)?;
+    }
+    if let Some(encoding) = config.from_encoding {
+        file = transcode::decode(file, encoding);
+    } else if config.strip_bom {
+        file = transcode::strip_bom(file).map_err(read_err)?;
+    }
+    Ok(file)
+}
+
+/// Opens a named input, refusing directories up front rather than failing
+/// on the first read.
+fn open_file(filename: &str) -> Result<File, CatError> {
+    let file = File::open(filename).map_err(|e| CatError::input(filename, e))?;
+    match file.metadata() {
+        Ok(meta) if meta.is_dir() => Err(CatError::IsDirectory {
+            filename: filename.to_string(),
+        }),
+        _ => Ok(file),
     }
 }
@@ -1,6 +1,11 @@
-fn main() {
-    if let Err(e) = catr::get_args().and_then(catr::run) {
-        eprintln!("{}", e);
-        std::process::exit(1);
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    match catr::get_args().and_then(catr::run) {
+        Ok(status) => status,
+        Err(e) => {
+            eprintln!("catr: {}", e);
+            ExitCode::FAILURE
+        }
     }
 }
@@ -0,0 +1,87 @@
+//! Opening and reading files ahead of the output on a pool of threads, for
+//! when opening many small files costs more than writing them.
+
+use crate::{open_file, CatError, Source};
+use std::collections::VecDeque;
+use std::io::Read;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Files up to this size are read whole; larger ones are only opened.
+const SMALL_FILE: u64 = 1024 * 1024;
+
+type Fetched = Result<Source, CatError>;
+type Job = (String, Sender<Fetched>);
+
+/// Files being fetched, handed back in the order they were given.
+pub struct Prefetch {
+    jobs: Sender<Job>,
+    /// One receiver per file started and not yet taken, in order.
+    pending: VecDeque<(String, Receiver<Fetched>)>,
+    queued: std::vec::IntoIter<String>,
+}
+
+impl Prefetch {
+    /// Starts fetching `files` on `workers` threads, keeping at most twice
+    /// that many files in memory at once.
+    pub fn new(files: Vec<String>, workers: usize) -> Self {
+        let (jobs, rx) = mpsc::channel::<Job>();
+        let rx = Arc::new(Mutex::new(rx));
+        for _ in 0..workers {
+            let rx = Arc::clone(&rx);
+            thread::spawn(move || loop {
+                let job = match rx.lock() {
+                    Ok(rx) => rx.recv(),
+                    Err(_) => break,
+                };
+                let Ok((filename, tx)) = job else { break };
+                let _ = tx.send(fetch(&filename));
+            });
+        }
+        let mut prefetch = Prefetch {
+            jobs,
+            pending: VecDeque::new(),
+            queued: files.into_iter(),
+        };
+        for _ in 0..workers * 2 {
+            prefetch.start_next();
+        }
+        prefetch
+    }
+
+    /// Waits for the next file, and starts fetching another in its place.
+    pub fn next(&mut self) -> Option<Fetched> {
+        let (filename, rx) = self.pending.pop_front()?;
+        self.start_next();
+        Some(rx.recv().unwrap_or_else(|_| {
+            Err(CatError::input(
+                &filename,
+                std::io::Error::other("read-ahead failed"),
+            ))
+        }))
+    }
+
+    fn start_next(&mut self) {
+        if let Some(filename) = self.queued.next() {
+            let (tx, rx) = mpsc::channel();
+            if self.jobs.send((filename.clone(), tx)).is_ok() {
+                self.pending.push_back((filename, rx));
+            }
+        }
+    }
+}
+
+/// Opens `filename`, and reads it too if it is a small regular file.
+fn fetch(filename: &str) -> Fetched {
+    let mut file = open_file(filename)?;
+    match file.metadata() {
+        Ok(meta) if meta.is_file() && meta.len() <= SMALL_FILE => {
+            let mut contents = Vec::with_capacity(meta.len() as usize);
+            file.read_to_end(&mut contents)
+                .map_err(|e| CatError::input(filename, e))?;
+            Ok(Source::Contents(contents))
+        }
+        _ => Ok(Source::File(file)),
+    }
+}
@@ -0,0 +1,123 @@
+//! Writing records last to first, like `tac`.
+
+use crate::copy::BUF_SIZE;
+use crate::{CatError, MyResult};
+use regex::bytes::Regex;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// What ends each record: a literal string or a regular expression.
+#[derive(Debug)]
+pub enum Separator {
+    Literal(Vec<u8>),
+    Regex(Regex),
+}
+
+impl Separator {
+    /// Builds a separator from the command-line value, read as a regular
+    /// expression if `regex` is set.
+    pub fn new(separator: &str, regex: bool) -> MyResult<Self> {
+        if separator.is_empty() {
+            return Err("separator cannot be empty".into());
+        }
+        Ok(if regex {
+            Separator::Regex(Regex::new(separator)?)
+        } else {
+            Separator::Literal(separator.as_bytes().to_vec())
+        })
+    }
+
+    /// The offsets just past each separator in `data`, in order. Literal
+    /// separators are searched for from the end, as tac does, so that
+    /// overlapping ones like `aa` in `aaa` split the same way.
+    fn ends(&self, data: &[u8]) -> Vec<usize> {
+        match self {
+            Separator::Literal(sep) => {
+                let mut ends = vec![];
+                let mut end = data.len();
+                while let Some(start) = data[..end].windows(sep.len()).rposition(|w| w == sep) {
+                    ends.push(start + sep.len());
+                    end = start;
+                }
+                ends.reverse();
+                ends
+            }
+            Separator::Regex(re) => re
+                .find_iter(data)
+                .filter(|m| !m.is_empty())
+                .map(|m| m.end())
+                .collect(),
+        }
+    }
+
+    /// Writes the records of `data` that are known to be complete, last
+    /// first, and returns how much of the start of `data` is left. A
+    /// separator at the very start may be the tail of a longer match that
+    /// only more input can show, so what comes before its end is held back
+    /// unless `at_start`.
+    fn write_records(
+        &self,
+        data: &[u8],
+        at_start: bool,
+        out: &mut impl Write,
+    ) -> io::Result<usize> {
+        let mut end = data.len();
+        for &start in self.ends(data).iter().rev() {
+            if start < end {
+                out.write_all(&data[start..end])?;
+                end = start;
+            }
+        }
+        if at_start {
+            out.write_all(&data[..end])?;
+            return Ok(0);
+        }
+        Ok(end)
+    }
+}
+
+/// Writes the records of a regular file last to first, reading it backwards
+/// a block at a time so it never has to be held in memory whole.
+pub fn reverse_file(
+    filename: &str,
+    mut file: File,
+    sep: &Separator,
+    out: &mut impl Write,
+) -> Result<(), CatError> {
+    let read_err = |e| CatError::input(filename, e);
+    let mut pos = file.seek(SeekFrom::End(0)).map_err(read_err)?;
+    let mut block_size = BUF_SIZE as u64;
+    // The part of the file from `pos` that hasn't been written yet.
+    let mut data = vec![];
+    while pos > 0 {
+        let len = block_size.min(pos);
+        pos -= len;
+        let mut block = vec![0; len as usize];
+        file.seek(SeekFrom::Start(pos)).map_err(read_err)?;
+        file.read_exact(&mut block).map_err(read_err)?;
+        block.extend_from_slice(&data);
+        data = block;
+        let left = sep.write_records(&data, pos == 0, out)?;
+        // A record longer than a block takes fewer, bigger reads to find.
+        if left == data.len() {
+            block_size *= 2;
+        }
+        data.truncate(left);
+    }
+    Ok(())
+}
+
+/// Writes the records of a stream last to first, which means reading all of
+/// it first.
+pub fn reverse_stream(
+    filename: &str,
+    mut file: impl Read,
+    sep: &Separator,
+    out: &mut impl Write,
+) -> Result<(), CatError> {
+    let mut data = vec![];
+    file.read_to_end(&mut data)
+        .map_err(|e| CatError::input(filename, e))?;
+    sep.write_records(&data, true, out)?;
+    Ok(())
+}
@@ -0,0 +1,157 @@
+//! Selecting which input lines are output, by line number and by regular
+//! expressions marking the start and end of a range.
+
+use crate::MyResult;
+use regex::bytes::Regex;
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+
+#[derive(Debug)]
+pub struct Selection {
+    /// 1-based, inclusive line ranges. Empty means every line.
+    lines: Vec<RangeInclusive<usize>>,
+    from: Option<Regex>,
+    to: Option<Regex>,
+    /// Lines of context shown before and after each selected block.
+    context: usize,
+}
+
+/// An input line with the number it is shown with, kept until it is known
+/// whether it will be output.
+#[derive(Debug)]
+pub struct HeldLine {
+    pub line_num: usize,
+    pub number: Option<usize>,
+    pub bytes: Vec<u8>,
+    pub newline: bool,
+}
+
+/// Progress through the input, kept across reopened files when following.
+#[derive(Debug, Default)]
+pub struct SelectState {
+    pattern: Pattern,
+    before: VecDeque<HeldLine>,
+    after: usize,
+}
+
+#[derive(Debug, Default, PartialEq)]
+enum Pattern {
+    #[default]
+    BeforeStart,
+    Inside,
+    Finished,
+}
+
+impl Selection {
+    /// Builds a selection from the command-line values, or `None` when no
+    /// option restricts the output.
+    pub fn new(
+        lines: Option<&str>,
+        from: Option<&str>,
+        to: Option<&str>,
+        context: usize,
+    ) -> MyResult<Option<Self>> {
+        if lines.is_none() && from.is_none() && to.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Selection {
+            lines: lines.map(parse_lines).transpose()?.unwrap_or_default(),
+            from: from.map(Regex::new).transpose()?,
+            to: to.map(Regex::new).transpose()?,
+            context,
+        }))
+    }
+
+    /// Feeds the next input line and returns the lines to output because of
+    /// it, in input order: any held-back context followed by the line itself.
+    pub fn push(&self, state: &mut SelectState, line: HeldLine) -> Vec<HeldLine> {
+        if self.matches(state, line.line_num, &line.bytes) {
+            state.after = self.context;
+            let mut shown: Vec<_> = state.before.drain(..).collect();
+            shown.push(line);
+            shown
+        } else if state.after > 0 {
+            state.after -= 1;
+            vec![line]
+        } else {
+            if self.context > 0 {
+                if state.before.len() == self.context {
+                    state.before.pop_front();
+                }
+                state.before.push_back(line);
+            }
+            vec![]
+        }
+    }
+
+    /// True once no line after `line_num` can be output.
+    pub fn is_done(&self, state: &SelectState, line_num: usize) -> bool {
+        let past_lines = self
+            .lines
+            .iter()
+            .map(|r| *r.end())
+            .max()
+            .is_some_and(|last| line_num >= last);
+        (past_lines || state.pattern == Pattern::Finished) && state.after == 0
+    }
+
+    fn matches(&self, state: &mut SelectState, line_num: usize, bytes: &[u8]) -> bool {
+        let in_pattern = match state.pattern {
+            Pattern::BeforeStart => {
+                let started = self.from.as_ref().is_none_or(|re| re.is_match(bytes));
+                if started {
+                    // A start marker doesn't also end the range on its own
+                    // line, but without one the end marker applies at once.
+                    let ends_here = self.from.is_none()
+                        && self.to.as_ref().is_some_and(|re| re.is_match(bytes));
+                    state.pattern = if ends_here {
+                        Pattern::Finished
+                    } else {
+                        Pattern::Inside
+                    };
+                }
+                started
+            }
+            Pattern::Inside => {
+                if self.to.as_ref().is_some_and(|re| re.is_match(bytes)) {
+                    state.pattern = Pattern::Finished;
+                }
+                true
+            }
+            Pattern::Finished => false,
+        };
+        in_pattern && (self.lines.is_empty() || self.lines.iter().any(|r| r.contains(&line_num)))
+    }
+}
+
+/// Parses a comma-separated list of line numbers and ranges such as
+/// `5,10-20,30-` or `-3`, where a missing end means the end of the input.
+fn parse_lines(list: &str) -> MyResult<Vec<RangeInclusive<usize>>> {
+    let parse_num = |s: &str| -> MyResult<usize> {
+        match s.parse() {
+            Ok(n) if n > 0 && s.bytes().all(|b| b.is_ascii_digit()) => Ok(n),
+            _ => Err(format!("invalid line range: \"{}\"", list).into()),
+        }
+    };
+    list.split(',')
+        .map(|part| match part.split_once('-') {
+            None => parse_num(part).map(|n| n..=n),
+            Some((start, end)) => {
+                let start = if start.is_empty() {
+                    1
+                } else {
+                    parse_num(start)?
+                };
+                let end = if end.is_empty() {
+                    usize::MAX
+                } else {
+                    parse_num(end)?
+                };
+                if start > end || part == "-" {
+                    return Err(format!("invalid line range: \"{}\"", list).into());
+                }
+                Ok(start..=end)
+            }
+        })
+        .collect()
+}
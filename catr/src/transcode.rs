@@ -0,0 +1,155 @@
+//! Converting input from other character encodings to UTF-8, and output
+//! from UTF-8 to another encoding.
+
+use encoding_rs::{CoderResult, Decoder, EncoderResult, Encoding, UTF_16BE, UTF_16LE};
+use std::io::{self, BufRead, Cursor, Read, Write};
+
+type Input = Box<dyn BufRead>;
+
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// Looks up an encoding by any of its WHATWG labels, such as `utf-16le`,
+/// `shift_jis` or `latin1`.
+pub fn encoding(label: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(label.as_bytes()).ok_or_else(|| format!("unknown encoding: \"{}\"", label))
+}
+
+/// Converts `input` from `encoding` to UTF-8. A byte order mark at the
+/// start overrides `encoding` and is removed, and malformed sequences are
+/// replaced with U+FFFD.
+pub fn decode(input: Input, encoding: &'static Encoding) -> Input {
+    Box::new(Decode {
+        input,
+        decoder: encoding.new_decoder(),
+        buf: vec![0; 8 * 1024],
+        pos: 0,
+        len: 0,
+        eof: false,
+    })
+}
+
+/// Removes a UTF-8 byte order mark from the start of `input`.
+pub fn strip_bom(mut input: Input) -> io::Result<Input> {
+    let mut header = Vec::with_capacity(UTF8_BOM.len());
+    (&mut input)
+        .take(UTF8_BOM.len() as u64)
+        .read_to_end(&mut header)?;
+    if header == UTF8_BOM {
+        header.clear();
+    }
+    Ok(Box::new(Cursor::new(header).chain(input)))
+}
+
+struct Decode {
+    input: Input,
+    decoder: Decoder,
+    buf: Vec<u8>,
+    pos: usize,
+    len: usize,
+    eof: bool,
+}
+
+impl Read for Decode {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(out.len());
+        out[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for Decode {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pos == self.len && !self.eof {
+            let src = self.input.fill_buf()?;
+            let last = src.is_empty();
+            let (result, read, written, _) = self.decoder.decode_to_utf8(src, &mut self.buf, last);
+            self.input.consume(read);
+            self.pos = 0;
+            self.len = written;
+            self.eof = last && result == CoderResult::InputEmpty;
+        }
+        Ok(&self.buf[self.pos..self.len])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.len);
+    }
+}
+
+/// A writer converting the UTF-8 written to it into another encoding.
+/// Characters the encoding can't represent are written as `?`.
+pub struct Encode<W: Write> {
+    inner: W,
+    encoding: &'static Encoding,
+    /// Reassembles characters split across writes.
+    utf8: Decoder,
+    encoder: encoding_rs::Encoder,
+}
+
+impl<W: Write> Encode<W> {
+    pub fn new(inner: W, encoding: &'static Encoding) -> Self {
+        Encode {
+            inner,
+            encoding,
+            utf8: encoding_rs::UTF_8.new_decoder_without_bom_handling(),
+            encoder: encoding.new_encoder(),
+        }
+    }
+
+    /// Writes out anything held back and ends the encoded text, as stateful
+    /// encodings like ISO-2022-JP need.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.encode(b"", true)
+    }
+
+    fn encode(&mut self, buf: &[u8], last: bool) -> io::Result<()> {
+        let mut text = String::with_capacity(
+            self.utf8
+                .max_utf8_buffer_length(buf.len())
+                .unwrap_or(buf.len() * 3 + 4),
+        );
+        let _ = self.utf8.decode_to_string(buf, &mut text, last);
+        let mut out = vec![];
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            // encoding_rs only decodes UTF-16, so it is encoded here.
+            for unit in text.encode_utf16() {
+                match self.encoding == UTF_16LE {
+                    true => out.extend_from_slice(&unit.to_le_bytes()),
+                    false => out.extend_from_slice(&unit.to_be_bytes()),
+                }
+            }
+        } else {
+            let mut text = &text[..];
+            loop {
+                out.reserve(
+                    self.encoder
+                        .max_buffer_length_from_utf8_without_replacement(text.len())
+                        .unwrap_or(text.len() * 4 + 16),
+                );
+                let (result, read) = self
+                    .encoder
+                    .encode_from_utf8_to_vec_without_replacement(text, &mut out, last);
+                text = &text[read..];
+                match result {
+                    EncoderResult::InputEmpty => break,
+                    EncoderResult::OutputFull => {}
+                    EncoderResult::Unmappable(_) => out.push(b'?'),
+                }
+            }
+        }
+        self.inner.write_all(&out)
+    }
+}
+
+impl<W: Write> Write for Encode<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.encode(buf, false)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,107 @@
+use catr::{Cat, NumberFormat, Numbering};
+use regex::bytes::Regex;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+
+type TestResult = Result<(), Box<dyn Error>>;
+
+const CONTROLS: &str = "tests/inputs/controls.txt";
+const BINARY: &str = "tests/inputs/binary.txt";
+const BUSTLE: &str = "tests/inputs/the-bustle.txt";
+
+// --------------------------------------------------
+fn copy(cat: &Cat, input_file: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut out = vec![];
+    cat.copy(fs::File::open(input_file)?, &mut out)?;
+    Ok(out)
+}
+
+// --------------------------------------------------
+#[test]
+fn copy_unchanged() -> TestResult {
+    assert_eq!(copy(&Cat::new(), BINARY)?, fs::read(BINARY)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn copy_numbered() -> TestResult {
+    let cat = Cat::new().number(Numbering::All);
+    assert_eq!(
+        copy(&cat, BINARY)?,
+        fs::read("tests/expected/binary.txt.n.out")?
+    );
+    let cat = Cat::new().number(Numbering::NonBlank);
+    assert_eq!(
+        copy(&cat, BUSTLE)?,
+        fs::read("tests/expected/the-bustle.txt.b.out")?
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn copy_number_format() -> TestResult {
+    let cat = Cat::new()
+        .number(Numbering::NonBlank)
+        .start(0)
+        .increment(-2)
+        .width(3)
+        .number_format(NumberFormat::Left)
+        .number_separator("|")
+        .pad_unnumbered(true);
+    let mut out = vec![];
+    cat.copy(&b"a\n\nb\n"[..], &mut out)?;
+    assert_eq!(out, b"0  |a\n    \n-2 |b\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn copy_show_all() -> TestResult {
+    let cat = Cat::new()
+        .show_ends(true)
+        .show_tabs(true)
+        .show_nonprinting(true);
+    assert_eq!(
+        copy(&cat, CONTROLS)?,
+        fs::read("tests/expected/controls.txt.A.out")?
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn writer_across_chunks() -> TestResult {
+    let input = fs::read(CONTROLS)?;
+    let expected = fs::read("tests/expected/controls.txt.ns.out")?;
+    let cat = Cat::new().number(Numbering::All).squeeze_blank(true);
+    for chunk_size in [1, 2, 3, 7, 64] {
+        let mut writer = cat.writer(vec![]);
+        for chunk in input.chunks(chunk_size) {
+            writer.write_all(chunk)?;
+        }
+        assert_eq!(writer.into_inner()?, expected, "chunks of {chunk_size}");
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn writer_classifies_whole_lines() -> TestResult {
+    let input = b"a\nab\n\\:\\:\nhead b\n\\:\\:\\:\nb\nab\nlast b";
+    let cat = Cat::new()
+        .number(Numbering::Matching(Regex::new("b$")?))
+        .header_numbering(Numbering::All)
+        .section_delimiter("\\:");
+    let expected = b"a\n     1\tab\n\n     1\thead b\n\n     1\tb\n     2\tab\n     3\tlast b";
+    for chunk_size in [1, 2, 3, 5] {
+        let mut writer = cat.writer(vec![]);
+        for chunk in input.chunks(chunk_size) {
+            writer.write_all(chunk)?;
+        }
+        assert_eq!(writer.into_inner()?, expected, "chunks of {chunk_size}");
+    }
+    Ok(())
+}
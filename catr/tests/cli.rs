@@ -3,6 +3,11 @@ use predicates::prelude::*;
 use rand::{distributions::Alphanumeric, Rng};
 use std::error::Error;
 use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
 
 type TestResult = Result<(), Box<dyn Error>>;
 
@@ -11,6 +16,22 @@ const EMPTY: &str = "tests/inputs/empty.txt";
 const FOX: &str = "tests/inputs/fox.txt";
 const SPIDERS: &str = "tests/inputs/spiders.txt";
 const BUSTLE: &str = "tests/inputs/the-bustle.txt";
+const CONTROLS: &str = "tests/inputs/controls.txt";
+const BINARY: &str = "tests/inputs/binary.txt";
+const ALL_GZ: &str = "tests/inputs/all.txt.gz";
+const FOX_ZST: &str = "tests/inputs/fox.txt.zst";
+const SPIDERS_XZ: &str = "tests/inputs/spiders.txt.xz";
+const BUSTLE_BZ2: &str = "tests/inputs/the-bustle.txt.bz2";
+const JSON: &str = "tests/inputs/config.json";
+const DIFF: &str = "tests/inputs/fix.diff";
+const GREET: &str = "tests/inputs/greet.txt";
+const LOG: &str = "tests/inputs/log.txt";
+const ZEROS: &str = "tests/inputs/zeros.bin";
+const SECTIONS: &str = "tests/inputs/sections.txt";
+const JAPANESE: &str = "tests/inputs/japanese.txt";
+const JAPANESE_SJIS: &str = "tests/inputs/japanese.sjis.txt";
+const JAPANESE_UTF16: &str = "tests/inputs/japanese.utf16le.txt";
+const JAPANESE_BOM: &str = "tests/inputs/japanese.bom.txt";
 
 // --------------------------------------------------
 #[test]
@@ -43,12 +64,75 @@ fn gen_bad_file() -> String {
 #[test]
 fn skips_bad_file() -> TestResult {
     let bad = gen_bad_file();
-    let expected = format!("{}: .* [(]os error 2[)]", bad);
+    let expected = format!("catr: {}: No such file or directory\n", bad);
     Command::cargo_bin(PRG)?
         .arg(&bad)
         .assert()
-        .success()
-        .stderr(predicate::str::is_match(expected)?);
+        .failure()
+        .stderr(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn continues_after_bad_file() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = fs::read_to_string("tests/expected/all.out")?;
+    for flag in &["-s", "--color=never"] {
+        Command::cargo_bin(PRG)?
+            .args([flag, FOX, &bad, SPIDERS, "tests", BUSTLE])
+            .assert()
+            .failure()
+            .stdout(expected.clone())
+            .stderr(format!(
+                "catr: {}: No such file or directory\n\
+                 catr: tests: Is a directory\n",
+                bad
+            ));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn reports_corrupt_input() -> TestResult {
+    let corrupt = std::env::temp_dir().join(format!("{}.gz", gen_bad_file()));
+    let mut data = fs::read(ALL_GZ)?;
+    data.truncate(data.len() / 2);
+    fs::write(&corrupt, data)?;
+    let corrupt = corrupt.to_string_lossy().into_owned();
+    let result = Command::cargo_bin(PRG)?
+        .args(["-z", &corrupt, FOX])
+        .assert()
+        .try_failure();
+    fs::remove_file(&corrupt)?;
+    result?
+        .stdout(predicate::str::ends_with(fs::read_to_string(FOX)?))
+        .stderr(predicate::str::starts_with(format!("catr: {}: ", corrupt)));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn quiet_on_broken_pipe() -> TestResult {
+    let mut child = std::process::Command::new(env!("CARGO_BIN_EXE_catr"))
+        .args(["-n", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    drop(child.stdout.take());
+    let mut stdin = child.stdin.take().unwrap();
+    let line = "the quick brown fox jumps over the lazy dog\n".repeat(1000);
+    for _ in 0..100 {
+        if stdin.write_all(line.as_bytes()).is_err() {
+            break;
+        }
+    }
+    drop(stdin);
+    let output = child.wait_with_output()?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stderr)?, "");
     Ok(())
 }
 
@@ -64,11 +148,18 @@ fn run(args: &[&str], expected_file: &str) -> TestResult {
 }
 
 // --------------------------------------------------
-fn run_stdin(
-    input_file: &str,
-    args: &[&str],
-    expected_file: &str,
-) -> TestResult {
+fn run_bytes(args: &[&str], expected_file: &str) -> TestResult {
+    let expected = fs::read(expected_file)?;
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run_stdin(input_file: &str, args: &[&str], expected_file: &str) -> TestResult {
     let input = fs::read_to_string(input_file)?;
     let expected = fs::read_to_string(expected_file)?;
     Command::cargo_bin(PRG)?
@@ -198,3 +289,656 @@ fn all_n() -> TestResult {
 fn all_b() -> TestResult {
     run(&[FOX, SPIDERS, BUSTLE, "-b"], "tests/expected/all.b.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn controls_show_all() -> TestResult {
+    run(&["-A", CONTROLS], "tests/expected/controls.txt.A.out")?;
+    run(
+        &["--show-all", CONTROLS],
+        "tests/expected/controls.txt.A.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn controls_show_ends() -> TestResult {
+    run(&["-E", CONTROLS], "tests/expected/controls.txt.E.out")?;
+    run(
+        &["--show-ends", CONTROLS],
+        "tests/expected/controls.txt.E.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn controls_show_tabs() -> TestResult {
+    run(&["-T", CONTROLS], "tests/expected/controls.txt.T.out")?;
+    run(
+        &["--show-tabs", CONTROLS],
+        "tests/expected/controls.txt.T.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn controls_show_nonprinting() -> TestResult {
+    run(&["-v", CONTROLS], "tests/expected/controls.txt.v.out")?;
+    run(
+        &["--show-nonprinting", CONTROLS],
+        "tests/expected/controls.txt.v.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn controls_e_t() -> TestResult {
+    run(&["-e", CONTROLS], "tests/expected/controls.txt.e.out")?;
+    run(&["-t", CONTROLS], "tests/expected/controls.txt.t.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn controls_squeeze_blank() -> TestResult {
+    run(&["-s", CONTROLS], "tests/expected/controls.txt.s.out")?;
+    run(
+        &["-n", "-s", CONTROLS],
+        "tests/expected/controls.txt.ns.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn controls_b_show_all() -> TestResult {
+    run(
+        &["-b", "-A", CONTROLS],
+        "tests/expected/controls.txt.bA.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn binary() -> TestResult {
+    run_bytes(&[BINARY], "tests/expected/binary.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_n() -> TestResult {
+    run_bytes(&["-n", BINARY], "tests/expected/binary.txt.n.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_b() -> TestResult {
+    run_bytes(&["-b", BINARY], "tests/expected/binary.txt.b.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_show_all() -> TestResult {
+    run_bytes(&["-A", BINARY], "tests/expected/binary.txt.A.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_squeeze_blank() -> TestResult {
+    run_bytes(&["-s", BINARY], "tests/expected/binary.txt.s.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_stdin() -> TestResult {
+    let input = fs::read(BINARY)?;
+    Command::cargo_bin(PRG)?
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout(input);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn large_file() -> TestResult {
+    let input: Vec<u8> = rand::thread_rng()
+        .sample_iter(rand::distributions::Standard)
+        .take(1 << 20)
+        .collect();
+    let path = std::env::temp_dir().join(gen_bad_file());
+    fs::write(&path, &input)?;
+    let path = path.to_string_lossy().into_owned();
+    let result = Command::cargo_bin(PRG)?
+        .args([&path, "-", &path])
+        .write_stdin(input.clone())
+        .assert()
+        .try_success();
+    fs::remove_file(&path)?;
+    result?.stdout(input.repeat(3));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn decompress_gzip_multi_member() -> TestResult {
+    run(&["-z", ALL_GZ], "tests/expected/all.out")?;
+    run(
+        &["-n", "--decompress", ALL_GZ],
+        "tests/expected/all.txt.gz.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn decompress_zstd() -> TestResult {
+    run(&["-z", FOX_ZST], "tests/expected/fox.txt.out")?;
+    run(&["-z", "-n", FOX_ZST], "tests/expected/fox.txt.n.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn decompress_xz() -> TestResult {
+    run(&["-z", SPIDERS_XZ], "tests/expected/spiders.txt.out")?;
+    run(
+        &["-z", "-b", SPIDERS_XZ],
+        "tests/expected/spiders.txt.b.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn decompress_bzip2() -> TestResult {
+    run(&["-z", BUSTLE_BZ2], "tests/expected/the-bustle.txt.out")?;
+    run(
+        &["-z", "-n", BUSTLE_BZ2],
+        "tests/expected/the-bustle.txt.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn decompress_stdin() -> TestResult {
+    let input = fs::read(BUSTLE_BZ2)?;
+    let expected = fs::read_to_string("tests/expected/the-bustle.txt.b.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-b"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn decompress_mixed() -> TestResult {
+    run(
+        &["-z", FOX_ZST, SPIDERS, BUSTLE_BZ2],
+        "tests/expected/all.out",
+    )?;
+    run(&["-z", EMPTY], "tests/expected/empty.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn compressed_without_decompress() -> TestResult {
+    let expected = fs::read(ALL_GZ)?;
+    Command::cargo_bin(PRG)?
+        .arg(ALL_GZ)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn color_always() -> TestResult {
+    run(
+        &["--color=always", JSON],
+        "tests/expected/config.json.color.out",
+    )?;
+    run(
+        &["--color", "always", "-n", DIFF],
+        "tests/expected/fix.diff.color.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn color_language() -> TestResult {
+    run(
+        &["--color=always", "--language", "sh", GREET],
+        "tests/expected/greet.txt.sh.out",
+    )?;
+    run(&["--color=always", GREET], GREET)
+}
+
+// --------------------------------------------------
+#[test]
+fn color_plain_when_piped() -> TestResult {
+    run(&[JSON], JSON)?;
+    run(&["--color=auto", DIFF], DIFF)?;
+    run(
+        &["--color=never", "-n", FOX],
+        "tests/expected/fox.txt.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_language() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--language", "cobol", FOX])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn lines_range() -> TestResult {
+    run(
+        &["-n", "--lines", "5-8", LOG],
+        "tests/expected/log.txt.lines.n.out",
+    )?;
+    run(
+        &["-n", "--lines", "3-4,20-", LOG],
+        "tests/expected/log.txt.lines-open.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn pattern_range() -> TestResult {
+    run(
+        &["--from", "^BEGIN", "--to", "^END", LOG],
+        "tests/expected/log.txt.from-to.out",
+    )?;
+    run(
+        &["-b", "--from", "^BEGIN", "--to", "^END", LOG],
+        "tests/expected/log.txt.from-to.b.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn pattern_range_context() -> TestResult {
+    run(
+        &["-n", "-C", "2", "--from", "^BEGIN", "--to", "^END", LOG],
+        "tests/expected/log.txt.from-to.C2.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn pattern_range_stdin() -> TestResult {
+    run_stdin(
+        LOG,
+        &["--from", "BEGIN", "--to", "END"],
+        "tests/expected/log.txt.from-to.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_range() -> TestResult {
+    for range in &["0", "2-1", "-", "1,,2", "a-3", "+4"] {
+        Command::cargo_bin(PRG)?
+            .args(["--lines", range, LOG])
+            .assert()
+            .failure()
+            .stderr(format!("catr: invalid line range: \"{}\"\n", range));
+    }
+    Command::cargo_bin(PRG)?
+        .args(["--from", "(", LOG])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("regex parse error"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn hex_dump() -> TestResult {
+    run(&["--hex", FOX], "tests/expected/fox.txt.hex.out")?;
+    run(&["--hex", ZEROS], "tests/expected/zeros.bin.hex.out")?;
+    run(&["--hex", EMPTY], "tests/expected/empty.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn octal_dump() -> TestResult {
+    run(&["--octal", ZEROS], "tests/expected/zeros.bin.octal.out")?;
+    run(
+        &[
+            "--octal", "--skip", "5", "--length", "40", "--width", "8", BUSTLE,
+        ],
+        "tests/expected/the-bustle.txt.octal.j5N40w8.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn hex_dump_decompressed() -> TestResult {
+    run(&["-z", "--hex", FOX_ZST], "tests/expected/fox.txt.hex.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_dump_options() -> TestResult {
+    for args in [
+        &["--hex", "--octal", FOX][..],
+        &["--hex", "--width", "0", FOX],
+        &["--octal", "--skip", "x", FOX],
+    ] {
+        Command::cargo_bin(PRG)?.args(args).assert().failure();
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn reverse() -> TestResult {
+    run(
+        &["--reverse", BUSTLE],
+        "tests/expected/the-bustle.txt.reverse.out",
+    )?;
+    run(&["--reverse", EMPTY], "tests/expected/empty.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn reverse_n() -> TestResult {
+    run(
+        &["--reverse", "-n", BUSTLE],
+        "tests/expected/the-bustle.txt.reverse.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn reverse_stdin() -> TestResult {
+    run_stdin(
+        BUSTLE,
+        &["--reverse"],
+        "tests/expected/the-bustle.txt.reverse.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn reverse_separator() -> TestResult {
+    run(
+        &["--reverse", "--separator", " ", FOX],
+        "tests/expected/fox.txt.reverse.space.out",
+    )?;
+    run(
+        &["--reverse", "--separator", "[ .]", "--regex", FOX],
+        "tests/expected/fox.txt.reverse.regex.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn reverse_across_blocks() -> TestResult {
+    // Longer than the blocks the file is read backwards in, with lines
+    // straddling block boundaries and no newline at the end.
+    let lines: Vec<String> = (0..40_000).map(|i| format!("line {}", i)).collect();
+    let path = std::env::temp_dir().join(format!("catr-reverse-{}", std::process::id()));
+    fs::write(&path, lines.join("\n"))?;
+    let mut expected = lines.last().unwrap().clone();
+    for line in lines.iter().rev().skip(1) {
+        expected.push_str(line);
+        expected.push('\n');
+    }
+    let output = Command::cargo_bin(PRG)?
+        .args(["--reverse", path.to_str().unwrap()])
+        .output()?;
+    fs::remove_file(&path)?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout)?, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_reverse_options() -> TestResult {
+    for args in [
+        &["--separator", ",", FOX][..],
+        &["--reverse", "--separator", "", FOX],
+        &["--reverse", "--separator", "(", "--regex", FOX],
+        &["--reverse", "--hex", FOX],
+    ] {
+        Command::cargo_bin(PRG)?.args(args).assert().failure();
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn number_start_increment() -> TestResult {
+    run(
+        &["-n", "--start", "10", "--increment", "5", BUSTLE],
+        "tests/expected/the-bustle.txt.n.v10i5.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn number_format() -> TestResult {
+    run(
+        &[
+            "-n",
+            "--number-format",
+            "rz",
+            "--width",
+            "3",
+            "--number-separator",
+            ": ",
+            SPIDERS,
+        ],
+        "tests/expected/spiders.txt.n.rz.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn number_sections() -> TestResult {
+    run(
+        &[
+            "--header-numbering",
+            "a",
+            "--body-numbering",
+            "t",
+            "--footer-numbering",
+            "a",
+            SECTIONS,
+        ],
+        "tests/expected/sections.txt.nl.out",
+    )?;
+    run(
+        &["--body-numbering", "p^b[13]", SECTIONS],
+        "tests/expected/sections.txt.bp.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_numbering() -> TestResult {
+    for args in [
+        &["--body-numbering", "x", FOX][..],
+        &["--body-numbering", "p(", FOX],
+        &["-n", "--width", "0", FOX],
+        &["-n", "--start", "one", FOX],
+        &["-n", "--body-numbering", "a", FOX],
+    ] {
+        Command::cargo_bin(PRG)?.args(args).assert().failure();
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn from_encoding() -> TestResult {
+    run(
+        &["-n", "--from-encoding", "shift_jis", JAPANESE_SJIS],
+        "tests/expected/japanese.txt.n.out",
+    )?;
+    // The byte order mark wins over the named encoding.
+    run(
+        &["-n", "--from-encoding", "shift_jis", JAPANESE_UTF16],
+        "tests/expected/japanese.txt.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn to_encoding() -> TestResult {
+    run_bytes(&["--to-encoding", "sjis", JAPANESE_UTF16], JAPANESE_SJIS)?;
+    // Output is written without a byte order mark.
+    let expected = fs::read(JAPANESE_UTF16)?;
+    Command::cargo_bin(PRG)?
+        .args(["--to-encoding", "utf-16le", JAPANESE_SJIS])
+        .args(["--from-encoding", "shift_jis"])
+        .assert()
+        .success()
+        .stdout(expected[2..].to_vec());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn strip_bom() -> TestResult {
+    run_bytes(&[JAPANESE_BOM], JAPANESE_BOM)?;
+    run_bytes(&["--strip-bom", JAPANESE_BOM], JAPANESE)?;
+    run_bytes(&["--strip-bom", JAPANESE], JAPANESE)
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_encoding() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["--from-encoding", "klingon", FOX])
+        .assert()
+        .failure()
+        .stderr("catr: unknown encoding: \"klingon\"\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn jobs_in_order() -> TestResult {
+    for jobs in ["1", "2", "8"] {
+        run(
+            &["-j", jobs, FOX, SPIDERS, BUSTLE],
+            "tests/expected/all.out",
+        )?;
+        run(
+            &["--jobs", jobs, "-n", FOX, SPIDERS, BUSTLE],
+            "tests/expected/all.n.out",
+        )?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn jobs_with_stdin_and_bad_files() -> TestResult {
+    let bad = gen_bad_file();
+    let expected = fs::read_to_string("tests/expected/all.out")?;
+    Command::cargo_bin(PRG)?
+        .args(["-j", "4", FOX, &bad, "-", "tests", BUSTLE])
+        .write_stdin(fs::read_to_string(SPIDERS)?)
+        .assert()
+        .failure()
+        .stdout(expected)
+        .stderr(format!(
+            "catr: {}: No such file or directory\n\
+             catr: tests: Is a directory\n",
+            bad
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_jobs() -> TestResult {
+    for jobs in ["0", "many"] {
+        Command::cargo_bin(PRG)?
+            .args(["-j", jobs, FOX])
+            .assert()
+            .failure()
+            .stderr(format!("catr: invalid jobs: \"{}\"\n", jobs));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+struct KillOnDrop(Child);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_rotation_and_truncation() -> TestResult {
+    let dir = std::env::temp_dir().join(gen_bad_file());
+    fs::create_dir(&dir)?;
+    let log = dir.join("app.log");
+    fs::write(&log, "one\ntwo\n")?;
+
+    let mut child = KillOnDrop(
+        std::process::Command::new(env!("CARGO_BIN_EXE_catr"))
+            .args(["-n", "--follow"])
+            .arg(&log)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?,
+    );
+    let stdout = child.0.stdout.take().unwrap();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let expect = |expected: &[&str]| -> TestResult {
+        for want in expected {
+            let line = rx.recv_timeout(Duration::from_secs(10))??;
+            assert_eq!(&line, want);
+        }
+        Ok(())
+    };
+    let append = |path: &std::path::Path, text: &str| -> TestResult {
+        fs::OpenOptions::new()
+            .append(true)
+            .open(path)?
+            .write_all(text.as_bytes())?;
+        Ok(())
+    };
+
+    expect(&["     1\tone", "     2\ttwo"])?;
+
+    append(&log, "three\n")?;
+    expect(&["     3\tthree"])?;
+
+    let rotated = dir.join("app.log.1");
+    fs::rename(&log, &rotated)?;
+    append(&rotated, "four\n")?;
+    fs::write(&log, "five\n")?;
+    expect(&["     4\tfour", "     5\tfive"])?;
+
+    fs::write(&log, "six\n")?;
+    expect(&["     6\tsix"])?;
+
+    drop(child);
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
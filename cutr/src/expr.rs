@@ -0,0 +1,776 @@
+//! The expressions of `-e`, which build output columns from fields:
+//! `$3` is the third field and `$name` (or `${some name}`) the one headed
+//! `name`, strings are written in double quotes, `.` joins values, and
+//! functions such as `upper($1)` transform them. The conditions of
+//! `--where` compare and match them, as in `$3 > 100 && $5 ~ /^EU/`.
+
+use crate::MyResult;
+use csv::StringRecord;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// An expression with the text it was parsed from.
+#[derive(Debug, Clone)]
+pub struct Expr {
+    src: String,
+    node: Node,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    /// A 0-based field position
+    Field(usize),
+    /// A field by header name, until resolved to its position
+    Named(String),
+    Literal(String),
+    Concat(Vec<Node>),
+    Call(Func, Vec<Node>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Func {
+    Upper,
+    Lower,
+    Trim,
+    Len,
+    Substr,
+    Replace,
+}
+
+impl Func {
+    fn new(name: &str) -> Option<Self> {
+        Some(match name {
+            "upper" => Func::Upper,
+            "lower" => Func::Lower,
+            "trim" => Func::Trim,
+            "len" => Func::Len,
+            "substr" => Func::Substr,
+            "replace" => Func::Replace,
+            _ => return None,
+        })
+    }
+
+    /// The least and most arguments the function takes.
+    fn arity(self) -> (usize, usize) {
+        match self {
+            Func::Upper | Func::Lower | Func::Trim | Func::Len => (1, 1),
+            Func::Substr => (2, 3),
+            Func::Replace => (3, 3),
+        }
+    }
+
+    fn call(self, args: &[String]) -> String {
+        match self {
+            Func::Upper => args[0].to_uppercase(),
+            Func::Lower => args[0].to_lowercase(),
+            Func::Trim => args[0].trim().to_string(),
+            Func::Len => args[0].chars().count().to_string(),
+            // Starts at a 1-based char, and runs to the end without a length.
+            Func::Substr => {
+                let start = args[1].trim().parse::<usize>().unwrap_or(1).max(1) - 1;
+                let len = args.get(2).map(|len| len.trim().parse().unwrap_or(0));
+                let chars = args[0].chars().skip(start);
+                match len {
+                    Some(len) => chars.take(len).collect(),
+                    None => chars.collect(),
+                }
+            }
+            Func::Replace => match args[1].is_empty() {
+                true => args[0].clone(),
+                false => args[0].replace(&args[1], &args[2]),
+            },
+        }
+    }
+}
+
+impl Expr {
+    /// Whether the expression names fields by header.
+    pub fn uses_names(&self) -> bool {
+        self.node.uses_names()
+    }
+
+    /// The expression with its field names turned into positions in
+    /// `headers`.
+    pub fn resolve(&self, headers: &StringRecord) -> MyResult<Expr> {
+        Ok(Expr {
+            src: self.src.clone(),
+            node: self.node.resolve(headers)?,
+        })
+    }
+
+    /// What to call the column: the header or `fN` of a lone field, or the
+    /// expression itself.
+    pub fn key(&self, headers: Option<&StringRecord>) -> String {
+        match self.node {
+            Node::Field(i) => match headers.and_then(|headers| headers.get(i)) {
+                Some(header) => header.to_string(),
+                None => format!("f{}", i + 1),
+            },
+            _ => self.src.clone(),
+        }
+    }
+
+    /// The value for `record`, where missing fields are empty.
+    pub fn eval(&self, record: &StringRecord) -> String {
+        self.node.eval(record)
+    }
+}
+
+impl Node {
+    fn uses_names(&self) -> bool {
+        match self {
+            Node::Named(_) => true,
+            Node::Field(_) | Node::Literal(_) => false,
+            Node::Concat(nodes) | Node::Call(_, nodes) => nodes.iter().any(Node::uses_names),
+        }
+    }
+
+    fn resolve(&self, headers: &StringRecord) -> MyResult<Node> {
+        Ok(match self {
+            Node::Named(name) => match headers.iter().position(|header| header == name) {
+                Some(i) => Node::Field(i),
+                None => return Err(format!("no field matches \"{}\"", name).into()),
+            },
+            Node::Concat(nodes) => Node::Concat(resolve_all(nodes, headers)?),
+            Node::Call(func, nodes) => Node::Call(*func, resolve_all(nodes, headers)?),
+            node => node.clone(),
+        })
+    }
+
+    fn eval(&self, record: &StringRecord) -> String {
+        match self {
+            Node::Field(i) => record.get(*i).unwrap_or_default().to_string(),
+            Node::Named(_) => unreachable!("resolved before evaluating"),
+            Node::Literal(value) => value.clone(),
+            Node::Concat(nodes) => nodes.iter().map(|node| node.eval(record)).collect(),
+            Node::Call(func, nodes) => {
+                let args: Vec<_> = nodes.iter().map(|node| node.eval(record)).collect();
+                func.call(&args)
+            }
+        }
+    }
+}
+
+fn resolve_all(nodes: &[Node], headers: &StringRecord) -> MyResult<Vec<Node>> {
+    nodes.iter().map(|node| node.resolve(headers)).collect()
+}
+
+/// A condition on the fields of a record.
+#[derive(Debug, Clone)]
+pub struct Cond {
+    pred: Pred,
+}
+
+#[derive(Debug, Clone)]
+enum Pred {
+    /// Compares numbers if both sides are ones, or else strings
+    Compare(Node, CmpOp, Node),
+    Matches(Node, Regex),
+    /// Whether a value is non-empty
+    Value(Node),
+    Not(Box<Pred>),
+    And(Vec<Pred>),
+    Or(Vec<Pred>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            CmpOp::Eq => ordering.is_eq(),
+            CmpOp::Ne => ordering.is_ne(),
+            CmpOp::Lt => ordering.is_lt(),
+            CmpOp::Le => ordering.is_le(),
+            CmpOp::Gt => ordering.is_gt(),
+            CmpOp::Ge => ordering.is_ge(),
+        }
+    }
+}
+
+impl Cond {
+    /// Whether the condition names fields by header.
+    pub fn uses_names(&self) -> bool {
+        self.pred.uses_names()
+    }
+
+    /// The condition with its field names turned into positions in
+    /// `headers`.
+    pub fn resolve(&self, headers: &StringRecord) -> MyResult<Cond> {
+        Ok(Cond {
+            pred: self.pred.resolve(headers)?,
+        })
+    }
+
+    /// Whether `record` meets the condition.
+    pub fn eval(&self, record: &StringRecord) -> bool {
+        self.pred.eval(record)
+    }
+}
+
+impl Pred {
+    fn uses_names(&self) -> bool {
+        match self {
+            Pred::Compare(left, _, right) => left.uses_names() || right.uses_names(),
+            Pred::Matches(node, _) | Pred::Value(node) => node.uses_names(),
+            Pred::Not(pred) => pred.uses_names(),
+            Pred::And(preds) | Pred::Or(preds) => preds.iter().any(Pred::uses_names),
+        }
+    }
+
+    fn resolve(&self, headers: &StringRecord) -> MyResult<Pred> {
+        let resolve_all = |preds: &[Pred]| -> MyResult<Vec<Pred>> {
+            preds.iter().map(|pred| pred.resolve(headers)).collect()
+        };
+        Ok(match self {
+            Pred::Compare(left, op, right) => {
+                Pred::Compare(left.resolve(headers)?, *op, right.resolve(headers)?)
+            }
+            Pred::Matches(node, re) => Pred::Matches(node.resolve(headers)?, re.clone()),
+            Pred::Value(node) => Pred::Value(node.resolve(headers)?),
+            Pred::Not(pred) => Pred::Not(Box::new(pred.resolve(headers)?)),
+            Pred::And(preds) => Pred::And(resolve_all(preds)?),
+            Pred::Or(preds) => Pred::Or(resolve_all(preds)?),
+        })
+    }
+
+    fn eval(&self, record: &StringRecord) -> bool {
+        match self {
+            Pred::Compare(left, op, right) => {
+                let (left, right) = (left.eval(record), right.eval(record));
+                let ordering = match (number(&left), number(&right)) {
+                    (Some(left), Some(right)) => match left.partial_cmp(&right) {
+                        Some(ordering) => ordering,
+                        None => return *op == CmpOp::Ne,
+                    },
+                    _ => left.cmp(&right),
+                };
+                op.holds(ordering)
+            }
+            Pred::Matches(node, re) => re.is_match(&node.eval(record)),
+            Pred::Value(node) => !node.eval(record).is_empty(),
+            Pred::Not(pred) => !pred.eval(record),
+            Pred::And(preds) => preds.iter().all(|pred| pred.eval(record)),
+            Pred::Or(preds) => preds.iter().any(|pred| pred.eval(record)),
+        }
+    }
+}
+
+/// The value of `value` if it is a decimal number, such as `-12` or
+/// `3.50`, allowing surrounding whitespace. Forms like `inf`, `NaN` and
+/// `1e3` are left to compare as strings.
+fn number(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let digits = value.strip_prefix(['+', '-']).unwrap_or(value);
+    let decimal = digits.bytes().any(|b| b.is_ascii_digit())
+        && digits.bytes().all(|b| b.is_ascii_digit() || b == b'.');
+    decimal.then(|| value.parse().ok()).flatten()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Field(usize),
+    Named(String),
+    Str(String),
+    Num(String),
+    Ident(String),
+    LParen,
+    RParen,
+    Comma,
+    Dot,
+    Cmp(CmpOp),
+    Match,
+    NotMatch,
+    Regex(String),
+    Not,
+    And,
+    Or,
+}
+
+/// Splits `src` into tokens, each with the byte offset it starts at.
+fn tokenize(src: &str) -> Result<Vec<(usize, Token)>, String> {
+    let mut tokens = vec![];
+    let mut chars = src.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            '.' => Token::Dot,
+            '=' | '!' | '<' | '>' | '&' | '|' | '~' => {
+                let next = chars.next_if(|&(_, next)| matches!(next, '=' | '&' | '|' | '~'));
+                match (c, next.map(|(_, next)| next)) {
+                    ('=', Some('=')) => Token::Cmp(CmpOp::Eq),
+                    ('!', Some('=')) => Token::Cmp(CmpOp::Ne),
+                    ('<', Some('=')) => Token::Cmp(CmpOp::Le),
+                    ('>', Some('=')) => Token::Cmp(CmpOp::Ge),
+                    ('<', None) => Token::Cmp(CmpOp::Lt),
+                    ('>', None) => Token::Cmp(CmpOp::Gt),
+                    ('!', Some('~')) => Token::NotMatch,
+                    ('!', None) => Token::Not,
+                    ('~', None) => Token::Match,
+                    ('&', Some('&')) => Token::And,
+                    ('|', Some('|')) => Token::Or,
+                    _ => return Err(format!("unexpected \"{}\" at {}", c, start)),
+                }
+            }
+            // A regular expression follows a match operator.
+            '/' if matches!(tokens.last(), Some((_, Token::Match | Token::NotMatch))) => {
+                let mut re = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err(format!("unterminated regex at {}", start)),
+                        Some((_, '/')) => break,
+                        Some((_, '\\')) if chars.peek().map(|&(_, c)| c) == Some('/') => {
+                            re.push('/');
+                            chars.next();
+                        }
+                        Some((_, c)) => re.push(c),
+                    }
+                }
+                Token::Regex(re)
+            }
+            '$' => {
+                let word = take_while(&mut chars, String::new(), is_name_char);
+                if word.is_empty() {
+                    match chars.next() {
+                        Some((_, '{')) => {
+                            let name: String = chars
+                                .by_ref()
+                                .map(|(_, c)| c)
+                                .take_while(|&c| c != '}')
+                                .collect();
+                            Token::Named(name)
+                        }
+                        _ => return Err(format!("expected a field after \"$\" at {}", start)),
+                    }
+                } else if word.chars().all(|c| c.is_ascii_digit()) {
+                    match word.parse::<usize>() {
+                        Ok(n) if n > 0 => Token::Field(n - 1),
+                        _ => return Err(format!("illegal field \"${}\" at {}", word, start)),
+                    }
+                } else {
+                    Token::Named(word)
+                }
+            }
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err(format!("unterminated string at {}", start)),
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, 'n')) => value.push('\n'),
+                            Some((_, 't')) => value.push('\t'),
+                            Some((_, c)) => value.push(c),
+                            None => return Err(format!("unterminated string at {}", start)),
+                        },
+                        Some((_, c)) => value.push(c),
+                    }
+                }
+                Token::Str(value)
+            }
+            c if c.is_ascii_digit() || c == '-' => {
+                let mut num = take_while(&mut chars, c.to_string(), |c| c.is_ascii_digit());
+                // A decimal point needs a digit after it, or it's a join.
+                let mut rest = chars.clone();
+                if let (Some((_, '.')), Some((_, d))) = (rest.next(), rest.next()) {
+                    if d.is_ascii_digit() {
+                        chars.next();
+                        num.push('.');
+                        num.push_str(&take_while(&mut chars, String::new(), |c| {
+                            c.is_ascii_digit()
+                        }));
+                    }
+                }
+                if num == "-" {
+                    return Err(format!("unexpected \"-\" at {}", start));
+                }
+                Token::Num(num)
+            }
+            c if is_name_char(c) => {
+                Token::Ident(take_while(&mut chars, c.to_string(), is_name_char))
+            }
+            c => return Err(format!("unexpected \"{}\" at {}", c, start)),
+        };
+        tokens.push((start, token));
+    }
+    Ok(tokens)
+}
+
+/// Appends to `word` the chars up to the first not matching `pred`.
+fn take_while(
+    chars: &mut Peekable<CharIndices>,
+    mut word: String,
+    pred: fn(char) -> bool,
+) -> String {
+    while let Some((_, c)) = chars.next_if(|&(_, c)| pred(c)) {
+        word.push(c);
+    }
+    word
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// A recursive descent parser over the tokens of one `-e` list.
+struct Parser<'a> {
+    src: &'a str,
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// Where the next token starts, for errors.
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.src.len(), |(i, _)| *i)
+    }
+
+    fn expect(&mut self, token: Token, what: &str) -> Result<(), String> {
+        match self.peek() == Some(&token) {
+            true => {
+                self.pos += 1;
+                Ok(())
+            }
+            false => Err(format!("expected {} at {}", what, self.offset())),
+        }
+    }
+
+    /// expr := value ("." value)*
+    fn expr(&mut self) -> Result<Node, String> {
+        let mut nodes = vec![self.value()?];
+        while self.peek() == Some(&Token::Dot) {
+            self.pos += 1;
+            nodes.push(self.value()?);
+        }
+        Ok(match nodes.len() {
+            1 => nodes.remove(0),
+            _ => Node::Concat(nodes),
+        })
+    }
+
+    /// value := field | string | number | name "(" expr ("," expr)* ")"
+    fn value(&mut self) -> Result<Node, String> {
+        let offset = self.offset();
+        Ok(match self.next() {
+            Some(Token::Field(i)) => Node::Field(i),
+            Some(Token::Named(name)) => Node::Named(name),
+            Some(Token::Str(value)) | Some(Token::Num(value)) => Node::Literal(value),
+            Some(Token::LParen) => {
+                let node = self.expr()?;
+                self.expect(Token::RParen, "\")\"")?;
+                node
+            }
+            Some(Token::Ident(name)) => {
+                let func = Func::new(&name)
+                    .ok_or_else(|| format!("unknown function \"{}\" at {}", name, offset))?;
+                self.expect(Token::LParen, "\"(\"")?;
+                let mut args = vec![self.expr()?];
+                while self.peek() == Some(&Token::Comma) {
+                    self.pos += 1;
+                    args.push(self.expr()?);
+                }
+                self.expect(Token::RParen, "\")\"")?;
+                let (min, max) = func.arity();
+                if args.len() < min || args.len() > max {
+                    return Err(format!(
+                        "wrong number of arguments to \"{}\" at {}",
+                        name, offset
+                    ));
+                }
+                Node::Call(func, args)
+            }
+            _ => return Err(format!("expected a value at {}", offset)),
+        })
+    }
+}
+
+impl Parser<'_> {
+    /// cond := all ("||" all)*
+    fn cond(&mut self) -> Result<Pred, String> {
+        let mut preds = vec![self.all()?];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            preds.push(self.all()?);
+        }
+        Ok(match preds.len() {
+            1 => preds.remove(0),
+            _ => Pred::Or(preds),
+        })
+    }
+
+    /// all := pred ("&&" pred)*
+    fn all(&mut self) -> Result<Pred, String> {
+        let mut preds = vec![self.pred()?];
+        while self.peek() == Some(&Token::And) {
+            self.pos += 1;
+            preds.push(self.pred()?);
+        }
+        Ok(match preds.len() {
+            1 => preds.remove(0),
+            _ => Pred::And(preds),
+        })
+    }
+
+    /// pred := "!" pred | "(" cond ")" | "empty" "(" expr ")"
+    ///       | expr (op expr | ("~" | "!~") (regex | string))?
+    fn pred(&mut self) -> Result<Pred, String> {
+        match self.peek() {
+            Some(Token::Not) => {
+                self.pos += 1;
+                return Ok(Pred::Not(Box::new(self.pred()?)));
+            }
+            // Parentheses may hold a condition, or start an expression.
+            Some(Token::LParen) => {
+                let start = self.pos;
+                self.pos += 1;
+                if let Ok(pred) = self.cond() {
+                    if self.peek() == Some(&Token::RParen) {
+                        self.pos += 1;
+                        if !matches!(
+                            self.peek(),
+                            Some(Token::Cmp(_) | Token::Match | Token::NotMatch | Token::Dot)
+                        ) {
+                            return Ok(pred);
+                        }
+                    }
+                }
+                self.pos = start;
+            }
+            Some(Token::Ident(name)) if name == "empty" => {
+                self.pos += 1;
+                self.expect(Token::LParen, "\"(\"")?;
+                let node = self.expr()?;
+                self.expect(Token::RParen, "\")\"")?;
+                return Ok(Pred::Not(Box::new(Pred::Value(node))));
+            }
+            _ => {}
+        }
+        let left = self.expr()?;
+        let offset = self.offset();
+        Ok(match self.peek().cloned() {
+            Some(Token::Cmp(op)) => {
+                self.pos += 1;
+                Pred::Compare(left, op, self.expr()?)
+            }
+            Some(token @ (Token::Match | Token::NotMatch)) => {
+                self.pos += 1;
+                let at = self.offset();
+                let re = match self.next() {
+                    Some(Token::Regex(re)) | Some(Token::Str(re)) => Regex::new(&re)
+                        .map_err(|e| format!("invalid regex at {}: {}", offset, e))?,
+                    _ => return Err(format!("expected a regex at {}", at)),
+                };
+                match token {
+                    Token::Match => Pred::Matches(left, re),
+                    _ => Pred::Not(Box::new(Pred::Matches(left, re))),
+                }
+            }
+            _ => Pred::Value(left),
+        })
+    }
+}
+
+/// Parses a condition, such as `$3 > 100 && ($5 ~ /^EU/ || empty($5))`.
+/// A value on its own holds when it is non-empty.
+pub fn parse_cond(src: &str) -> MyResult<Cond> {
+    let invalid = |e: String| format!("invalid condition \"{}\": {}", src, e);
+    let mut parser = Parser {
+        src,
+        tokens: tokenize(src).map_err(invalid)?,
+        pos: 0,
+    };
+    let pred = parser.cond().map_err(invalid)?;
+    match parser.peek() {
+        None => Ok(Cond { pred }),
+        Some(_) => Err(invalid(format!("unexpected token at {}", parser.offset())).into()),
+    }
+}
+
+/// Parses a comma-separated list of expressions, such as
+/// `$3,upper($1),$2 . "-" . $4`.
+pub fn parse_exprs(src: &str) -> MyResult<Vec<Expr>> {
+    let invalid = |e: String| format!("invalid expression \"{}\": {}", src, e);
+    let mut parser = Parser {
+        src,
+        tokens: tokenize(src).map_err(invalid)?,
+        pos: 0,
+    };
+    let mut exprs = vec![];
+    loop {
+        let start = parser.offset();
+        let node = parser.expr().map_err(invalid)?;
+        exprs.push(Expr {
+            src: src[start..parser.offset()].trim().to_string(),
+            node,
+        });
+        let offset = parser.offset();
+        match parser.next() {
+            None => return Ok(exprs),
+            Some(Token::Comma) => {}
+            _ => return Err(invalid(format!("expected \",\" at {}", offset)).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_cond, parse_exprs};
+    use csv::StringRecord;
+    use pretty_assertions::assert_eq;
+
+    fn eval(src: &str, record: &[&str]) -> Vec<String> {
+        let record = StringRecord::from(record.to_vec());
+        let exprs = parse_exprs(src).unwrap();
+        exprs.iter().map(|expr| expr.eval(&record)).collect()
+    }
+
+    #[test]
+    fn test_eval() {
+        let record = ["ada", " Lovelace ", "1815"];
+        assert_eq!(eval("$3,$1,$1", &record), ["1815", "ada", "ada"]);
+        assert_eq!(
+            eval(r#"upper($1) . "-" . trim($2)"#, &record),
+            ["ADA-Lovelace"]
+        );
+        assert_eq!(eval("len($2), len($9), $9", &record), ["10", "0", ""]);
+        assert_eq!(
+            eval(
+                r#"substr($2, 2, 4), substr($3,3), replace($1, "a", "A")"#,
+                &record
+            ),
+            ["Love", "15", "AdA"]
+        );
+        assert_eq!(
+            eval(r#"1.5, "a,b\"c", lower("X").1"#, &record),
+            ["1.5", "a,b\"c", "x1"]
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        let headers = StringRecord::from(vec!["id", "full name"]);
+        let exprs = parse_exprs("$2, ${full name} . $id, len($1)").unwrap();
+        assert!(!exprs[0].uses_names());
+        assert!(exprs[1].uses_names());
+        let resolved = exprs[1].resolve(&headers).unwrap();
+        let record = StringRecord::from(vec!["7", "Ada"]);
+        assert_eq!(resolved.eval(&record), "Ada7");
+        assert_eq!(exprs[0].key(Some(&headers)), "full name");
+        assert_eq!(exprs[0].key(None), "f2");
+        assert_eq!(exprs[2].key(None), "len($1)");
+        let res = parse_exprs("$nope").unwrap()[0].resolve(&headers);
+        assert_eq!(res.unwrap_err().to_string(), r#"no field matches "nope""#);
+    }
+
+    #[test]
+    fn test_cond() {
+        let holds = |src: &str, record: &[&str]| {
+            let record = StringRecord::from(record.to_vec());
+            parse_cond(src).unwrap().eval(&record)
+        };
+        let record = ["widget", " 150", "EU-West", ""];
+        assert!(holds(r#"$2 > 100 && $3 ~ /^EU/"#, &record));
+        assert!(!holds("$2 > 1000", &record));
+        // Strings compare as strings: "widget" sorts after "9".
+        assert!(holds("$2 >= 150.0 && $2 < 950 && $1 > 9", &record));
+        assert!(holds(r#"$1 == "widget" && $1 != "w""#, &record));
+        assert!(holds("$3 !~ /west/ && $3 ~ \"(?i)west\"", &record));
+        assert!(holds("empty($4) && !$4 && $1 && !empty($9 . $1)", &record));
+        assert!(holds("$4 || ($2 > 100 && !($1 ~ /x/))", &record));
+        assert!(holds("(len($1) . \"\") == 6", &record));
+        assert!(holds(r#"$3 ~ /^EU\/?-/"#, &record));
+
+        // Only decimal numbers compare as numbers.
+        let record = ["inf", "nan", "1e3", "-2.5", ".5"];
+        assert!(holds(
+            r#"$1 == "inf" && $2 == "nan" && $2 != "NaN""#,
+            &record
+        ));
+        assert!(!holds(r#"$1 == "Infinity""#, &record));
+        assert!(!holds("$3 == 1000", &record));
+        assert!(holds("$4 < -2 && $5 == 0.50 && $4 < $5", &record));
+    }
+
+    #[test]
+    fn test_cond_names() {
+        let cond = parse_cond("$price >= 10 && ${the region} ~ /^EU/").unwrap();
+        assert!(cond.uses_names());
+        let headers = StringRecord::from(vec!["the region", "price"]);
+        let cond = cond.resolve(&headers).unwrap();
+        assert!(cond.eval(&StringRecord::from(vec!["EU", "10"])));
+        assert!(!cond.eval(&StringRecord::from(vec!["US", "10"])));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = |src: &str| parse_exprs(src).unwrap_err().to_string();
+        assert_eq!(
+            err("$0"),
+            r#"invalid expression "$0": illegal field "$0" at 0"#
+        );
+        assert_eq!(
+            err("foo($1)"),
+            r#"invalid expression "foo($1)": unknown function "foo" at 0"#
+        );
+        assert_eq!(
+            err("upper($1, $2)"),
+            r#"invalid expression "upper($1, $2)": wrong number of arguments to "upper" at 0"#
+        );
+        assert_eq!(
+            err(r#"$1 . "x"#),
+            r#"invalid expression "$1 . "x": unterminated string at 5"#
+        );
+        assert_eq!(
+            err("$1 $2"),
+            r#"invalid expression "$1 $2": expected "," at 3"#
+        );
+        assert_eq!(
+            err("$1,"),
+            r#"invalid expression "$1,": expected a value at 3"#
+        );
+
+        let err = |src: &str| parse_cond(src).unwrap_err().to_string();
+        assert_eq!(
+            err("$1 = 2"),
+            r#"invalid condition "$1 = 2": unexpected "=" at 3"#
+        );
+        assert_eq!(
+            err("$1 ~ $2"),
+            r#"invalid condition "$1 ~ $2": expected a regex at 5"#
+        );
+        assert_eq!(
+            err("$1 ~ /a"),
+            r#"invalid condition "$1 ~ /a": unterminated regex at 5"#
+        );
+        assert_eq!(
+            err("$1 > 2 $3"),
+            r#"invalid condition "$1 > 2 $3": unexpected token at 7"#
+        );
+        assert!(err("$1 ~ /(/").starts_with(r#"invalid condition "$1 ~ /(/": invalid regex at 3"#));
+    }
+}
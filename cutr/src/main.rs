@@ -1,22 +1,155 @@
+mod expr;
+
 use clap::Parser;
-use csv::{ReaderBuilder, StringRecord, WriterBuilder};
+use csv::{ReaderBuilder, StringRecord, Terminator, WriterBuilder};
+use expr::{parse_cond, parse_exprs, Cond, Expr};
+use regex::Regex;
 use std::{
+    borrow::Cow,
     error::Error,
-    fs::File,
-    io::{self, BufRead, BufReader},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Write},
+    iter,
     ops::Range,
+    slice,
 };
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 type MyResult<T> = Result<T, Box<dyn Error>>;
-type PositionList = Vec<Range<usize>>;
+type PositionList = Vec<Position>;
+
+/// A range of 0-based positions from `start` up to `end`, or to the end of
+/// the line when `end` is open, as in `5-`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    start: usize,
+    end: Option<usize>,
+}
 
 #[derive(Debug)]
 pub enum Extract {
     Fields(PositionList),
+    FieldNames(Vec<FieldName>),
     Bytes(PositionList),
     Chars(PositionList),
+    Columns(Vec<Column>),
+    Exprs(Vec<Expr>),
+}
+
+/// What `-c` positions count.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CharUnit {
+    /// Unicode scalar values
+    Scalar,
+    /// Extended grapheme clusters, so that a letter keeps its accents and
+    /// an emoji sequence stays whole
+    Grapheme,
+    /// Terminal cells, where a grapheme cluster is at the cell it starts in
+    /// and wide ones, such as CJK, take two
+    DisplayColumn,
 }
 
+/// A named fixed-width column, as in `name:9-40`.
+#[derive(Debug)]
+pub struct Column {
+    name: String,
+    pos: Position,
+}
+
+/// A field picked by its header: by exact name, by a glob such as `addr_*`,
+/// or by a regular expression written between slashes, as in `/^e-?mail$/`.
+#[derive(Debug)]
+pub struct FieldName {
+    name: String,
+    pattern: Option<Regex>,
+}
+
+impl FieldName {
+    fn matches(&self, header: &str) -> bool {
+        match &self.pattern {
+            Some(re) => re.is_match(header),
+            None => header == self.name,
+        }
+    }
+}
+
+/// Where plain lines are split into fields.
+#[derive(Debug)]
+enum Splitter {
+    /// At every occurrence of a string, which may be longer than a byte
+    Literal(String),
+    /// At every match of a regular expression
+    Regex(Regex),
+    /// At runs of whitespace, ignoring any at the start and end, like awk
+    Whitespace,
+}
+
+impl Splitter {
+    fn split<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        match self {
+            Splitter::Literal(delimiter) => line.split(delimiter.as_str()).collect(),
+            Splitter::Regex(re) => re.split(line).collect(),
+            Splitter::Whitespace => line.split_whitespace().collect(),
+        }
+    }
+
+    /// Whether `line` has more than one field.
+    fn is_delimited(&self, line: &str) -> bool {
+        self.split(line).len() > 1
+    }
+
+    /// What selected fields are joined with when no output delimiter is
+    /// given: the delimiter itself, or a space when it can vary.
+    fn output_delimiter(&self) -> &str {
+        match self {
+            Splitter::Literal(delimiter) => delimiter,
+            _ => " ",
+        }
+    }
+}
+
+/// How lines are split into fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Format {
+    /// Split at every delimiter, without quoting, like cut
+    Plain,
+    /// RFC 4180: fields may be quoted to hold delimiters, quotes and
+    /// newlines, and are quoted on output where needed. The delimiter
+    /// defaults to a comma
+    Csv,
+    /// Tab-separated, with the same quoting as csv
+    Tsv,
+    /// JSON Lines: a JSON value per line, with the fields given by --keys
+    Jsonl,
+}
+
+/// How selected fields and columns are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum OutputFormat {
+    /// Joined with the output delimiter, and quoted as the input format is
+    Plain,
+    /// Comma-separated, quoted where needed
+    Csv,
+    /// Tab-separated, quoted where needed
+    Tsv,
+    /// An array of objects keyed by column or header name, or f1..fN
+    Json,
+    /// An object per line keyed by column or header name, or f1..fN
+    Jsonl,
+    /// An array per line
+    JsonlArrays,
+}
+
+/// The selections the fixed-width column options don't apply to.
+const NOT_COLUMNS: [&str; 5] = ["fields", "field_names", "bytes", "chars", "exprs"];
+
+/// The selections the options for bytes don't apply to.
+const NOT_BYTES: [&str; 5] = ["fields", "field_names", "chars", "columns", "exprs"];
+
+/// The selections the options counting chars don't apply to.
+const NOT_CHARS: [&str; 5] = ["fields", "field_names", "bytes", "columns", "exprs"];
+
 #[derive(Parser, Debug)]
 #[command(version, about)]
 /// A clone of `cut` written in Rust
@@ -25,9 +158,104 @@ struct Args {
     #[arg(default_value = "-")]
     files: Vec<String>,
 
-    /// Field delimiter
-    #[arg(short, long, value_name = "DELIMITER", default_value = "\t")]
-    delimiter: String,
+    /// Field delimiter [default: tab, or comma with --format=csv]
+    #[arg(short, long, value_name = "DELIMITER")]
+    delimiter: Option<String>,
+
+    /// Split fields at matches of a regular expression
+    #[arg(long, value_name = "REGEX", conflicts_with = "delimiter")]
+    regex_delimiter: Option<String>,
+
+    /// Split fields at runs of whitespace, ignoring leading and trailing
+    /// whitespace
+    #[arg(short, long, conflicts_with_all = ["delimiter", "regex_delimiter"])]
+    whitespace: bool,
+
+    /// How fields are split and quoted
+    #[arg(
+        long,
+        visible_alias = "input-format",
+        value_enum,
+        default_value_t = Format::Plain
+    )]
+    format: Format,
+
+    /// The keys, or /JSON/pointers, whose values are the fields of each
+    /// line with --format=jsonl, such as level,msg,/req/id
+    #[arg(long, value_name = "KEYS")]
+    keys: Option<String>,
+
+    /// Output delimiter [default: the input delimiter for fields, or a
+    /// space with --regex-delimiter or --whitespace; a tab for columns;
+    /// none for bytes and chars]
+    #[arg(long, value_name = "STRING")]
+    output_delimiter: Option<String>,
+
+    /// Select everything except the given bytes, chars or fields
+    #[arg(long, conflicts_with_all = ["exprs", "columns"])]
+    complement: bool,
+
+    /// Print selections in the order given, repeating any selected twice,
+    /// instead of once each in input order
+    #[arg(long, conflicts_with_all = ["exprs", "columns"])]
+    keep_order: bool,
+
+    /// Skip lines not containing the delimiter, instead of printing them
+    /// whole
+    #[arg(short = 's', long, conflicts_with_all = ["exprs", "columns"])]
+    only_delimited: bool,
+
+    /// Lines end with NUL instead of newline
+    #[arg(short, long)]
+    zero_terminated: bool,
+
+    /// Treat the first line of each file as a header and print its selected
+    /// fields first; jsonl input has none, its fields being named by --keys
+    #[arg(long, conflicts_with_all = ["bytes", "chars", "columns"])]
+    header: bool,
+
+    /// Don't split multibyte characters: a byte range selects the
+    /// characters whose last byte it holds
+    #[arg(short = 'n', conflicts_with_all = NOT_BYTES)]
+    no_split: bool,
+
+    /// Write selected bytes as they are, instead of replacing broken UTF-8
+    /// with U+FFFD or dropping the \r of a \r\n
+    #[arg(long, conflicts_with_all = NOT_BYTES)]
+    raw: bool,
+
+    /// Count chars as grapheme clusters, such as a letter with its accents
+    /// or an emoji sequence, instead of Unicode scalar values
+    #[arg(long, conflicts_with_all = NOT_CHARS)]
+    graphemes: bool,
+
+    /// Count chars as terminal cells, with wide ones such as CJK taking two;
+    /// a grapheme cluster is selected by the cell it starts in
+    #[arg(long, conflicts_with_all = NOT_CHARS)]
+    display_columns: bool,
+
+    /// Count fixed-width column ranges in bytes instead of chars, and write
+    /// them as they are, whatever their encoding
+    #[arg(long, conflicts_with_all = NOT_COLUMNS)]
+    byte_columns: bool,
+
+    /// Trim whitespace padding from fixed-width columns
+    #[arg(long, conflicts_with_all = NOT_COLUMNS)]
+    trim: bool,
+
+    /// How selected fields and columns are written; for columns, csv and
+    /// tsv start with a header of the column names
+    #[arg(long, value_enum, default_value_t = OutputFormat::Plain)]
+    output_format: OutputFormat,
+
+    /// Only print records meeting a condition, such as
+    /// '$3 > 100 && $region ~ /^EU/'
+    #[arg(
+        long = "where",
+        value_name = "CONDITION",
+        conflicts_with_all = ["bytes", "chars", "columns"]
+    )]
+    filter: Option<String>,
 
     #[command(flatten)]
     extract: ArgsExtract,
@@ -37,16 +265,30 @@ struct Args {
 #[group(required = true, multiple = false)]
 struct ArgsExtract {
     /// Selected fields
-    #[arg(short, long, value_name = "FIELDS")]
+    #[arg(short, long, value_name = "FIELDS", allow_hyphen_values = true)]
     fields: Option<String>,
 
+    /// Selected fields by header name, glob or /regex/, in the order named
+    #[arg(short = 'F', long, value_name = "NAMES")]
+    field_names: Option<String>,
+
     /// Selected bytes
-    #[arg(short, long, value_name = "BYTES")]
+    #[arg(short, long, value_name = "BYTES", allow_hyphen_values = true)]
     bytes: Option<String>,
 
     /// Selected chars
-    #[arg(short, long, value_name = "CHARS")]
+    #[arg(short, long, value_name = "CHARS", allow_hyphen_values = true)]
     chars: Option<String>,
+
+    /// Output columns built from fields, such as
+    /// '$3,upper($1),$2 . "-" . $4,len($name)'
+    #[arg(short, long = "expr", value_name = "EXPRS", allow_hyphen_values = true)]
+    exprs: Option<String>,
+
+    /// Fixed-width columns as NAME:RANGE, such as id:1-8,name:9-40, or
+    /// @FILE to read them from a file
+    #[arg(long, value_name = "SPEC")]
+    columns: Option<String>,
 }
 
 fn main() {
@@ -58,53 +300,636 @@ fn main() {
 
 fn run(args: Args) -> MyResult<()> {
     // dbg!(&args);
-    let delimiter = if args.delimiter.len() == 1 {
-        args.delimiter.as_bytes()[0]
-    } else {
-        return Err(format!(r#"--delim "{}" must be a single byte"#, args.delimiter).into());
-    };
-    let extract = if let Some(fields) = args.extract.fields.map(parse_pos).transpose()? {
+    let splitter = parse_splitter(&args)?;
+    match (args.format, &args.keys) {
+        (Format::Jsonl, None) => return Err("--format=jsonl needs --keys".into()),
+        // Lines of JSON have no header; their fields are named by --keys.
+        (Format::Jsonl, Some(_)) if args.header => {
+            return Err("--header can't be used with --format=jsonl".into())
+        }
+        (Format::Jsonl, Some(_)) | (_, None) => {}
+        (_, Some(_)) => return Err("--keys needs --format=jsonl".into()),
+    }
+    let extract = if let Some(fields) = args.extract.fields.clone().map(parse_pos).transpose()? {
         Extract::Fields(fields)
-    } else if let Some(bytes) = args.extract.bytes.map(parse_pos).transpose()? {
+    } else if let Some(names) = args.extract.field_names.as_deref() {
+        Extract::FieldNames(parse_field_names(names)?)
+    } else if let Some(bytes) = args.extract.bytes.clone().map(parse_pos).transpose()? {
         Extract::Bytes(bytes)
-    } else if let Some(chars) = args.extract.chars.map(parse_pos).transpose()? {
+    } else if let Some(chars) = args.extract.chars.clone().map(parse_pos).transpose()? {
         Extract::Chars(chars)
+    } else if let Some(spec) = args.extract.columns.as_deref() {
+        Extract::Columns(parse_columns(spec)?)
+    } else if let Some(exprs) = args.extract.exprs.as_deref() {
+        Extract::Exprs(parse_exprs(exprs)?)
     } else {
         return Err("No extract option provided".into());
     };
+    let filter = args.filter.as_deref().map(parse_cond).transpose()?;
+    let pos = match &extract {
+        Extract::Columns(columns) => return cut_columns(columns, &args, io::stdout().lock()),
+        Extract::Fields(_) | Extract::FieldNames(_) | Extract::Exprs(_) => {
+            let out = io::stdout().lock();
+            return cut_fields(&extract, filter.as_ref(), &args, &splitter, out);
+        }
+        Extract::Bytes(pos) | Extract::Chars(pos) => pos,
+    };
+    let terminator = if args.zero_terminated { b'\0' } else { b'\n' };
+    let char_unit = match (args.display_columns, args.graphemes) {
+        (true, _) => CharUnit::DisplayColumn,
+        (false, true) => CharUnit::Grapheme,
+        (false, false) => CharUnit::Scalar,
+    };
+    let output_delimiter = args.output_delimiter.as_deref().unwrap_or("");
+    let mut out = io::stdout().lock();
     for filename in &args.files {
         match open(filename) {
             Err(err) => eprintln!("{}: {}", filename, err),
-            Ok(file) => match &extract {
-                Extract::Fields(field_pos) => {
-                    let mut reader = ReaderBuilder::new()
-                        .delimiter(delimiter)
-                        .has_headers(false)
-                        .from_reader(file);
-                    let mut writer = WriterBuilder::new()
-                        .delimiter(delimiter)
-                        .from_writer(io::stdout());
-                    for record in reader.records() {
-                        let record = record?;
-                        writer.write_record(extract_fields(&record, field_pos))?;
+            Ok(mut file) => {
+                let mut buf = vec![];
+                loop {
+                    buf.clear();
+                    if file.read_until(terminator, &mut buf)? == 0 {
+                        break;
+                    }
+                    if buf.last() == Some(&terminator) {
+                        buf.pop();
+                        // Raw bytes are written as they are, `\r` included.
+                        if terminator == b'\n' && !args.raw && buf.last() == Some(&b'\r') {
+                            buf.pop();
+                        }
                     }
+                    if args.only_delimited && !splitter.is_delimited(&String::from_utf8_lossy(&buf))
+                    {
+                        continue;
+                    }
+                    let selected = match &extract {
+                        Extract::Bytes(_) => {
+                            let pos = select(pos, buf.len(), args.keep_order, args.complement);
+                            let pos = match args.no_split {
+                                true => snap_to_chars(&buf, &pos),
+                                false => pos,
+                            };
+                            let selected =
+                                extract_raw_bytes(&buf, &pos, output_delimiter.as_bytes());
+                            match args.raw || args.no_split {
+                                true => selected,
+                                false => String::from_utf8_lossy(&selected).into_owned().into(),
+                            }
+                        }
+                        _ => {
+                            let line = std::str::from_utf8(&buf)?;
+                            let (units, len) = split_chars(line, char_unit);
+                            let pos = select(pos, len, args.keep_order, args.complement);
+                            extract_units(&units, &pos, output_delimiter).into()
+                        }
+                    };
+                    out.write_all(&selected)?;
+                    out.write_all(&[terminator])?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Builds the field splitter from the delimiter options. The csv reader
+/// only splits on single bytes.
+fn parse_splitter(args: &Args) -> MyResult<Splitter> {
+    let splitter = if let Some(re) = &args.regex_delimiter {
+        let re = Regex::new(re).map_err(|e| format!("invalid --regex-delimiter: {}", e))?;
+        if re.is_match("") {
+            return Err(format!(
+                r#"--regex-delimiter "{}" must not match an empty string"#,
+                re
+            )
+            .into());
+        }
+        Splitter::Regex(re)
+    } else if args.whitespace {
+        Splitter::Whitespace
+    } else {
+        let delimiter = match (args.delimiter.as_deref(), args.format) {
+            (Some(delimiter), _) => delimiter,
+            (None, Format::Csv) => ",",
+            (None, _) => "\t",
+        };
+        if delimiter.is_empty() {
+            return Err(r#"--delim "" must not be empty"#.into());
+        }
+        Splitter::Literal(delimiter.to_string())
+    };
+    match (&splitter, args.format) {
+        (_, Format::Plain | Format::Jsonl) => {}
+        (Splitter::Literal(delimiter), _) if delimiter.len() == 1 => {}
+        (Splitter::Literal(delimiter), _) => {
+            return Err(format!(r#"--delim "{}" must be a single byte"#, delimiter).into());
+        }
+        _ => return Err("--regex-delimiter and --whitespace need --format=plain".into()),
+    }
+    Ok(splitter)
+}
+
+/// Writes the selected fields of the records of the input files, through
+/// one writer so that json gets a single array.
+fn cut_fields(
+    extract: &Extract,
+    filter: Option<&Cond>,
+    args: &Args,
+    splitter: &Splitter,
+    out: impl Write,
+) -> MyResult<()> {
+    let terminator = if args.zero_terminated { b'\0' } else { b'\n' };
+    let output_delimiter = args
+        .output_delimiter
+        .as_deref()
+        .unwrap_or(splitter.output_delimiter())
+        .as_bytes();
+    let quote = matches!(args.format, Format::Csv | Format::Tsv);
+    let mut writer =
+        RecordWriter::with_format(args.output_format, output_delimiter, quote, terminator, out);
+    for filename in &args.files {
+        match open(filename) {
+            Err(err) => eprintln!("{}: {}", filename, err),
+            Ok(file) => cut_records(filename, file, extract, filter, args, splitter, &mut writer)?,
+        }
+    }
+    writer.finish()
+}
+
+/// Writes the selected fields of each record of `file` meeting `filter`,
+/// starting with the header when there is one.
+fn cut_records<W: Write>(
+    filename: &str,
+    file: impl BufRead,
+    extract: &Extract,
+    filter: Option<&Cond>,
+    args: &Args,
+    splitter: &Splitter,
+    writer: &mut RecordWriter<W>,
+) -> MyResult<()> {
+    // JSON Lines fields are named by their keys rather than a first line,
+    // so there is no header to print.
+    let has_headers = args.format != Format::Jsonl
+        && (args.header
+            || match extract {
+                Extract::FieldNames(_) => true,
+                Extract::Exprs(exprs) => exprs.iter().any(Expr::uses_names),
+                _ => false,
+            }
+            || filter.is_some_and(Cond::uses_names));
+    let terminator = if args.zero_terminated { b'\0' } else { b'\n' };
+    let keys: Vec<_> = args.keys.iter().flat_map(|keys| keys.split(',')).collect();
+    let mut records: Box<dyn Iterator<Item = MyResult<StringRecord>>> = match args.format {
+        Format::Plain => Box::new(
+            lines(file, terminator).map(|line| Ok(StringRecord::from_iter(splitter.split(&line?)))),
+        ),
+        Format::Csv | Format::Tsv => {
+            let delimiter = match splitter {
+                Splitter::Literal(delimiter) => delimiter.as_bytes()[0],
+                _ => unreachable!("checked by parse_splitter"),
+            };
+            let mut reader = ReaderBuilder::new();
+            reader
+                .delimiter(delimiter)
+                .has_headers(false)
+                .flexible(true);
+            if args.zero_terminated {
+                reader.terminator(Terminator::Any(b'\0'));
+            }
+            let records = reader.from_reader(file).into_records();
+            Box::new(records.map(|record| record.map_err(Into::into)))
+        }
+        Format::Jsonl => Box::new(
+            lines(file, terminator)
+                .enumerate()
+                .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()))
+                .map(|(i, line)| {
+                    json_fields(&line?, &keys)
+                        .map_err(|e| format!("{}: line {}: {}", filename, i + 1, e).into())
+                }),
+        ),
+    };
+    let headers = match (args.format, has_headers) {
+        (Format::Jsonl, _) => Some(StringRecord::from_iter(&keys)),
+        (_, true) => records.next().transpose()?,
+        (_, false) => None,
+    };
+    let exprs = match extract {
+        Extract::Exprs(exprs) => {
+            let headers = headers.clone().unwrap_or_default();
+            let exprs = exprs.iter().map(|expr| match expr.uses_names() {
+                true => expr.resolve(&headers),
+                false => Ok(expr.clone()),
+            });
+            let exprs: MyResult<Vec<_>> = exprs.collect();
+            Some(exprs.map_err(|e| format!("{}: {}", filename, e))?)
+        }
+        _ => None,
+    };
+    let filter = match filter {
+        Some(filter) if filter.uses_names() => Some(
+            filter
+                .resolve(&headers.clone().unwrap_or_default())
+                .map_err(|e| format!("{}: {}", filename, e))?,
+        ),
+        filter => filter.cloned(),
+    };
+    let (field_pos, keep_order) = match extract {
+        Extract::Exprs(_) => (Cow::Owned(vec![]), false),
+        Extract::FieldNames(names) => {
+            let headers = headers.clone().unwrap_or_default();
+            let field_pos =
+                resolve_field_names(names, &headers).map_err(|e| format!("{}: {}", filename, e))?;
+            (Cow::Owned(field_pos), true)
+        }
+        Extract::Fields(field_pos) => (Cow::Borrowed(field_pos), args.keep_order),
+        _ => unreachable!("not a field selection"),
+    };
+    let keyed = writer.is_keyed();
+    let mut write = |record: &StringRecord, is_header: bool| -> MyResult<()> {
+        if !is_header && filter.as_ref().is_some_and(|filter| !filter.eval(record)) {
+            return Ok(());
+        }
+        if let Some(exprs) = &exprs {
+            // Expressions are headed by their keys.
+            let keys = exprs.iter().map(|expr| expr.key(headers.as_ref()));
+            let values = exprs.iter().map(|expr| expr.eval(record));
+            return match (is_header, keyed) {
+                (true, _) => writer.write(keys),
+                (false, true) => writer.write_keyed(keys, values),
+                (false, false) => writer.write(values),
+            };
+        }
+        // Like cut, lines without a delimiter are kept whole. Records of the
+        // structured formats always have fields, even if only one.
+        let undelimited = args.format == Format::Plain && record.len() <= 1;
+        if undelimited && args.only_delimited {
+            return Ok(());
+        }
+        let field_pos = match undelimited {
+            true => iter::once(0..record.len()).collect(),
+            false => select(&field_pos, record.len(), keep_order, args.complement),
+        };
+        let fields = extract_fields(record, &field_pos);
+        if !keyed {
+            return writer.write(fields);
+        }
+        let keys = field_pos.iter().cloned().flatten().map(|i| {
+            match headers.as_ref().and_then(|headers| headers.get(i)) {
+                Some(header) => Cow::Borrowed(header),
+                None => Cow::Owned(format!("f{}", i + 1)),
+            }
+        });
+        writer.write_keyed(keys, fields)
+    };
+    // Objects are keyed by the header instead.
+    if has_headers && !keyed {
+        if let Some(headers) = &headers {
+            write(headers, true)?;
+        }
+    }
+    for record in records {
+        write(&record?, false)?;
+    }
+    Ok(())
+}
+
+/// Writes the fixed-width `columns` of each line of the input files,
+/// through one writer so that csv and tsv get a single header and json a
+/// single array.
+fn cut_columns(columns: &[Column], args: &Args, out: impl Write) -> MyResult<()> {
+    let terminator = if args.zero_terminated { b'\0' } else { b'\n' };
+    let names: Vec<_> = columns.iter().map(|column| column.name.clone()).collect();
+    let delimiter = args.output_delimiter.as_deref().unwrap_or("\t");
+    let mut writer = RecordWriter::with_format(
+        args.output_format,
+        delimiter.as_bytes(),
+        false,
+        terminator,
+        out,
+    );
+    if matches!(args.output_format, OutputFormat::Csv | OutputFormat::Tsv) {
+        writer.write(&names)?;
+    }
+    for filename in &args.files {
+        match open(filename) {
+            Err(err) => eprintln!("{}: {}", filename, err),
+            Ok(file) => {
+                for line in file.split(terminator) {
+                    let line = line.map_err(|e| format!("{}: {}", filename, e))?;
+                    let values = match args.byte_columns {
+                        // Fixed-width data is often in a legacy encoding, so
+                        // its bytes are sliced as they are.
+                        true => byte_columns(&line, columns, args.trim),
+                        false => {
+                            let mut line = line.as_slice();
+                            if terminator == b'\n' {
+                                line = line.strip_suffix(b"\r").unwrap_or(line);
+                            }
+                            let line = std::str::from_utf8(line)
+                                .map_err(|e| format!("{}: {}", filename, e))?;
+                            char_columns(line, columns, args.trim)
+                        }
+                    };
+                    writer.write_keyed(&names, values)?;
                 }
-                Extract::Bytes(bytes_pos) => {
-                    for line in file.lines() {
-                        println!("{}", extract_bytes(&line?, bytes_pos));
+            }
+        }
+    }
+    writer.finish()
+}
+
+/// The bytes of each of `columns` in `line`, with ASCII whitespace trimmed
+/// if `trim` is set.
+fn byte_columns(line: &[u8], columns: &[Column], trim: bool) -> Vec<Vec<u8>> {
+    columns
+        .iter()
+        .map(|column| {
+            let pos = select(slice::from_ref(&column.pos), line.len(), true, false);
+            let value = extract_raw_bytes(line, &pos, b"");
+            match trim {
+                true => value.trim_ascii().to_vec(),
+                false => value,
+            }
+        })
+        .collect()
+}
+
+/// The chars of each of `columns` in `line`, with whitespace trimmed if
+/// `trim` is set.
+fn char_columns(line: &str, columns: &[Column], trim: bool) -> Vec<Vec<u8>> {
+    let len = line.chars().count();
+    columns
+        .iter()
+        .map(|column| {
+            let pos = select(slice::from_ref(&column.pos), len, true, false);
+            let value = extract_chars(line, &pos, "");
+            match trim {
+                true => value.trim().into(),
+                false => value.into(),
+            }
+        })
+        .collect()
+}
+
+/// The values of `keys` in a line of JSON, where keys starting with `/` are
+/// JSON pointers. Strings are taken as they are, nulls and missing values
+/// are empty, and anything else is written as JSON.
+fn json_fields(line: &str, keys: &[&str]) -> MyResult<StringRecord> {
+    let value: serde_json::Value = serde_json::from_str(line)?;
+    Ok(keys
+        .iter()
+        .map(|key| {
+            let field = match key.starts_with('/') {
+                true => value.pointer(key),
+                false => value.get(key),
+            };
+            match field {
+                None | Some(serde_json::Value::Null) => String::new(),
+                Some(serde_json::Value::String(field)) => field.clone(),
+                Some(field) => field.to_string(),
+            }
+        })
+        .collect())
+}
+
+/// The lines of `file`, without their terminator or the `\r` of a `\r\n`.
+fn lines(file: impl BufRead, terminator: u8) -> impl Iterator<Item = MyResult<String>> {
+    file.split(terminator).map(move |line| {
+        let mut line = line?;
+        if terminator == b'\n' && line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Ok(String::from_utf8(line)?)
+    })
+}
+
+/// Writes selected fields joined by the output delimiter, either through
+/// the csv writer, which quotes fields where needed, or as they are; or as
+/// JSON, in one array or a value per line.
+enum RecordWriter<W: Write> {
+    Csv(Box<csv::Writer<W>>),
+    Joined {
+        delimiter: Vec<u8>,
+        terminator: u8,
+        out: W,
+    },
+    Json {
+        started: bool,
+        out: W,
+    },
+    Jsonl {
+        arrays: bool,
+        terminator: u8,
+        out: W,
+    },
+}
+
+impl<W: Write> RecordWriter<W> {
+    fn new(delimiter: &[u8], terminator: u8, out: W) -> Self {
+        match delimiter {
+            &[delimiter] => RecordWriter::Csv(Box::new(
+                WriterBuilder::new()
+                    .delimiter(delimiter)
+                    .flexible(true)
+                    .terminator(Terminator::Any(terminator))
+                    .from_writer(out),
+            )),
+            _ => Self::joined(delimiter, terminator, out),
+        }
+    }
+
+    /// A writer putting `delimiter` between fields without quoting them.
+    fn joined(delimiter: &[u8], terminator: u8, out: W) -> Self {
+        RecordWriter::Joined {
+            delimiter: delimiter.to_vec(),
+            terminator,
+            out,
+        }
+    }
+
+    /// A writer for `format`. Plain output is joined with `delimiter`, and
+    /// only quoted if `quote` is set.
+    fn with_format(
+        format: OutputFormat,
+        delimiter: &[u8],
+        quote: bool,
+        terminator: u8,
+        out: W,
+    ) -> Self {
+        match format {
+            OutputFormat::Plain if quote => Self::new(delimiter, terminator, out),
+            OutputFormat::Plain => Self::joined(delimiter, terminator, out),
+            OutputFormat::Csv => Self::new(b",", terminator, out),
+            OutputFormat::Tsv => Self::new(b"\t", terminator, out),
+            OutputFormat::Json => RecordWriter::Json {
+                started: false,
+                out,
+            },
+            OutputFormat::Jsonl => RecordWriter::Jsonl {
+                arrays: false,
+                terminator,
+                out,
+            },
+            OutputFormat::JsonlArrays => RecordWriter::Jsonl {
+                arrays: true,
+                terminator,
+                out,
+            },
+        }
+    }
+
+    /// Whether records are written as objects, which need their keys.
+    fn is_keyed(&self) -> bool {
+        matches!(
+            self,
+            RecordWriter::Json { .. } | RecordWriter::Jsonl { arrays: false, .. }
+        )
+    }
+
+    /// Writes `fields`, keyed `f1` to `fN` if written as objects.
+    fn write<I, T>(&mut self, fields: I) -> MyResult<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        let fields: Vec<T> = fields.into_iter().collect();
+        let keys: Vec<_> = (1..=fields.len()).map(|i| format!("f{}", i)).collect();
+        self.write_keyed(keys, fields)
+    }
+
+    /// Writes `fields`, keyed by `keys` if written as objects.
+    fn write_keyed<K, I, T>(&mut self, keys: K, fields: I) -> MyResult<()>
+    where
+        K: IntoIterator,
+        K::Item: AsRef<str>,
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        match self {
+            RecordWriter::Csv(writer) => writer.write_record(fields)?,
+            RecordWriter::Joined {
+                delimiter,
+                terminator,
+                out,
+            } => {
+                for (i, field) in fields.into_iter().enumerate() {
+                    if i > 0 {
+                        out.write_all(delimiter)?;
                     }
+                    out.write_all(field.as_ref())?;
                 }
-                Extract::Chars(char_pos) => {
-                    for line in file.lines() {
-                        println!("{}", extract_chars(&line?, char_pos));
+                out.write_all(&[*terminator])?;
+            }
+            RecordWriter::Json { started, out } => {
+                out.write_all(if *started { b",\n" } else { b"[\n" })?;
+                *started = true;
+                write_json_object(out, keys, fields)?;
+            }
+            RecordWriter::Jsonl {
+                arrays: false,
+                terminator,
+                out,
+            } => {
+                write_json_object(&mut *out, keys, fields)?;
+                out.write_all(&[*terminator])?;
+            }
+            RecordWriter::Jsonl {
+                arrays: true,
+                terminator,
+                out,
+            } => {
+                out.write_all(b"[")?;
+                for (i, field) in fields.into_iter().enumerate() {
+                    if i > 0 {
+                        out.write_all(b",")?;
                     }
+                    serde_json::to_writer(&mut *out, &String::from_utf8_lossy(field.as_ref()))?;
                 }
-            },
+                out.write_all(b"]")?;
+                out.write_all(&[*terminator])?;
+            }
         }
+        Ok(())
     }
+
+    /// Ends the output, closing the JSON array, and flushes it.
+    fn finish(self) -> MyResult<()> {
+        match self {
+            RecordWriter::Csv(mut writer) => writer.flush()?,
+            RecordWriter::Joined { mut out, .. } | RecordWriter::Jsonl { mut out, .. } => {
+                out.flush()?
+            }
+            RecordWriter::Json { started, mut out } => {
+                out.write_all(if started { b"\n]\n" } else { b"[]\n" })?;
+                out.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes `fields` as the string values of a JSON object keyed by `keys`.
+fn write_json_object<K, I, T>(mut out: impl Write, keys: K, fields: I) -> MyResult<()>
+where
+    K: IntoIterator,
+    K::Item: AsRef<str>,
+    I: IntoIterator<Item = T>,
+    T: AsRef<[u8]>,
+{
+    out.write_all(b"{")?;
+    for (i, (key, field)) in keys.into_iter().zip(fields).enumerate() {
+        if i > 0 {
+            out.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut out, key.as_ref())?;
+        out.write_all(b":")?;
+        serde_json::to_writer(&mut out, &String::from_utf8_lossy(field.as_ref()))?;
+    }
+    out.write_all(b"}")?;
     Ok(())
 }
 
+/// Resolves `pos` against a line of `len` bytes, chars or fields. Open
+/// ends reach the end of the line, and unless `keep_order` is set the
+/// ranges are sorted and overlapping ones merged, so that each position is
+/// extracted once and in line order. With `complement` the runs of
+/// positions not selected are returned instead.
+fn select(pos: &[Position], len: usize, keep_order: bool, complement: bool) -> Vec<Range<usize>> {
+    let mut ranges: Vec<_> = pos
+        .iter()
+        .map(|p| p.start..p.end.unwrap_or(len).min(len))
+        .filter(|range| !range.is_empty())
+        .collect();
+    if keep_order && !complement {
+        return ranges;
+    }
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = vec![];
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start < last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    if !complement {
+        return merged;
+    }
+    let mut runs = vec![];
+    let mut start = 0;
+    for range in merged {
+        if start < range.start {
+            runs.push(start..range.start);
+        }
+        start = range.end;
+    }
+    if start < len {
+        runs.push(start..len);
+    }
+    runs
+}
+
 fn parse_pos(range: String) -> MyResult<PositionList> {
     let mut result: PositionList = vec![];
     for part in range.split(",") {
@@ -116,38 +941,171 @@ fn parse_pos(range: String) -> MyResult<PositionList> {
                 }
                 Ok(num)
             } else {
-                return Err(format!("illegal list value: \"{}\"", part).into());
+                Err(format!("illegal list value: \"{}\"", part).into())
             }
         };
         if part.contains("-") {
             let mut parts = part.split("-");
             let start = parts.next().unwrap();
             let end = parts.next().unwrap();
-            if start.is_empty() || end.is_empty() {
-                return Err(format!(
-                    "First number in range ({}) must be lower than second number ({})",
-                    start, end
-                )
-                .into());
-            }
-            let start = into_valid_number(start)?;
-            let end = into_valid_number(end)?;
-            if start >= end {
-                return Err(format!(
-                    "First number in range ({}) must be lower than second number ({})",
-                    start, end
-                )
-                .into());
+            if (start.is_empty() && end.is_empty()) || parts.next().is_some() {
+                return Err(format!("illegal list value: \"{}\"", part).into());
             }
-            result.push(start - 1..end);
+            // A missing start is the first position, and a missing end the
+            // last one on each line.
+            let end = match end {
+                "" => None,
+                end => Some(into_valid_number(end)?),
+            };
+            let start = match start {
+                "" => 1,
+                start => {
+                    let start = into_valid_number(start)?;
+                    if let Some(end) = end.filter(|&end| start >= end) {
+                        return Err(format!(
+                            "First number in range ({}) must be lower than second number ({})",
+                            start, end
+                        )
+                        .into());
+                    }
+                    start
+                }
+            };
+            result.push(Position {
+                start: start - 1,
+                end,
+            });
         } else {
             let pos = into_valid_number(part)?;
-            result.push(pos - 1..pos);
+            result.push(Position {
+                start: pos - 1,
+                end: Some(pos),
+            });
         }
     }
     Ok(result)
 }
 
+/// Parses a comma-separated list of header names, globs and `/regexes/`.
+fn parse_field_names(names: &str) -> MyResult<Vec<FieldName>> {
+    names
+        .split(',')
+        .map(|name| {
+            if name.is_empty() {
+                return Err(format!("illegal field name list: \"{}\"", names).into());
+            }
+            let pattern = match name.strip_prefix('/').and_then(|n| n.strip_suffix('/')) {
+                Some(re) => Some(re.to_string()),
+                None if name.contains(['*', '?', '[']) => Some(glob_to_regex(name)),
+                None => None,
+            };
+            let pattern = pattern
+                .map(|re| Regex::new(&re))
+                .transpose()
+                .map_err(|e| format!("invalid field name \"{}\": {}", name, e))?;
+            Ok(FieldName {
+                name: name.to_string(),
+                pattern,
+            })
+        })
+        .collect()
+}
+
+/// Translates a shell glob matching a whole name into a regular expression.
+fn glob_to_regex(glob: &str) -> String {
+    let mut re = String::from("^");
+    let mut in_class = false;
+    for c in glob.chars() {
+        match c {
+            '*' if !in_class => re.push_str(".*"),
+            '?' if !in_class => re.push('.'),
+            '[' if !in_class => {
+                in_class = true;
+                re.push('[');
+            }
+            ']' if in_class => {
+                in_class = false;
+                re.push(']');
+            }
+            '!' if in_class && re.ends_with('[') => re.push('^'),
+            '\\' | '[' if in_class => {
+                re.push('\\');
+                re.push(c);
+            }
+            c if in_class => re.push(c),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    re
+}
+
+/// Parses fixed-width column specs such as `id:1-8,name:9-40`, or reads
+/// them from the file named after an `@`. A spec file may hold one or more
+/// to a line, and skips blank lines and `#` comments.
+fn parse_columns(spec: &str) -> MyResult<Vec<Column>> {
+    let spec = match spec.strip_prefix('@') {
+        Some(filename) => {
+            fs::read_to_string(filename).map_err(|e| format!("{}: {}", filename, e))?
+        }
+        None => spec.to_string(),
+    };
+    let mut columns: Vec<Column> = vec![];
+    for line in spec.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        for column in line.split(',') {
+            let invalid = || format!("invalid column spec: \"{}\"", column);
+            let (name, range) = column
+                .split_once(':')
+                .filter(|(name, _)| !name.trim().is_empty())
+                .ok_or_else(invalid)?;
+            let mut pos =
+                parse_pos(range.trim().to_string()).map_err(|e| format!("{}: {}", invalid(), e))?;
+            // Names key the json output, where a repeated one would be lost.
+            let name = name.trim();
+            if columns.iter().any(|column| column.name == name) {
+                return Err(format!("duplicate column name '{}'", name).into());
+            }
+            columns.push(Column {
+                name: name.to_string(),
+                pos: pos.remove(0),
+            });
+        }
+    }
+    if columns.is_empty() {
+        return Err("no columns given".into());
+    }
+    Ok(columns)
+}
+
+/// The positions of the fields `names` pick out of `headers`, in the order
+/// named. A field picked by several names is only taken the first time.
+fn resolve_field_names(names: &[FieldName], headers: &StringRecord) -> MyResult<PositionList> {
+    let mut field_pos: PositionList = vec![];
+    for name in names {
+        let matching: Vec<_> = headers
+            .iter()
+            .enumerate()
+            .filter(|(_, header)| name.matches(header))
+            .map(|(i, _)| Position {
+                start: i,
+                end: Some(i + 1),
+            })
+            .collect();
+        if matching.is_empty() {
+            return Err(format!("no field matches \"{}\"", name.name).into());
+        }
+        for pos in matching {
+            if !field_pos.contains(&pos) {
+                field_pos.push(pos);
+            }
+        }
+    }
+    Ok(field_pos)
+}
+
 fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     match filename {
         "-" => Ok(Box::new(BufReader::new(io::stdin()))),
@@ -155,22 +1113,94 @@ fn open(filename: &str) -> MyResult<Box<dyn BufRead>> {
     }
 }
 
-fn extract_chars(line: &str, char_pos: &[Range<usize>]) -> String {
-    let chars: Vec<_> = line.chars().collect();
-    char_pos
+fn extract_chars(line: &str, char_pos: &[Range<usize>], delimiter: &str) -> String {
+    extract_units(&split_chars(line, CharUnit::Scalar).0, char_pos, delimiter)
+}
+
+/// Splits `line` into the chars `unit` counts, each with its position, and
+/// returns them with the number of positions in the line.
+fn split_chars(line: &str, unit: CharUnit) -> (Vec<(usize, &str)>, usize) {
+    let units: Vec<_> = match unit {
+        CharUnit::Scalar => line
+            .char_indices()
+            .map(|(i, c)| &line[i..i + c.len_utf8()])
+            .enumerate()
+            .collect(),
+        CharUnit::Grapheme => line.graphemes(true).enumerate().collect(),
+        CharUnit::DisplayColumn => {
+            let mut column = 0;
+            let units = line
+                .graphemes(true)
+                .map(|grapheme| {
+                    let start = column;
+                    column += grapheme.width();
+                    (start, grapheme)
+                })
+                .collect();
+            return (units, column);
+        }
+    };
+    let len = units.len();
+    (units, len)
+}
+
+/// Joins the units positioned in each of `pos`, which `units` are sorted by.
+fn extract_units(units: &[(usize, &str)], pos: &[Range<usize>], delimiter: &str) -> String {
+    pos.iter()
+        .map(|range| {
+            let start = units.partition_point(|(i, _)| *i < range.start);
+            let end = units.partition_point(|(i, _)| *i < range.end);
+            units[start..end.max(start)]
+                .iter()
+                .map(|(_, unit)| *unit)
+                .collect::<String>()
+        })
+        .filter(|selected| !selected.is_empty())
+        .collect::<Vec<_>>()
+        .join(delimiter)
+}
+
+/// Joins the bytes of each of `bytes_pos`, whether or not they are UTF-8.
+fn extract_raw_bytes(line: &[u8], bytes_pos: &[Range<usize>], delimiter: &[u8]) -> Vec<u8> {
+    bytes_pos
         .iter()
         .cloned()
-        .flat_map(|range| range.filter_map(|i| chars.get(i)))
-        .collect()
+        .map(|range| {
+            range
+                .filter_map(|i| line.get(i))
+                .copied()
+                .collect::<Vec<_>>()
+        })
+        .filter(|selected| !selected.is_empty())
+        .collect::<Vec<_>>()
+        .join(delimiter)
 }
-fn extract_bytes(line: &str, bytes_pos: &[Range<usize>]) -> String {
-    let bytes = line.as_bytes();
-    let selected: Vec<_> = bytes_pos
+
+/// Moves the ends of `bytes_pos` to character boundaries in `line`, so
+/// that each range holds the characters whose last byte it held, as
+/// `cut -n` does. Bytes that aren't UTF-8 are characters of their own.
+fn snap_to_chars(line: &[u8], bytes_pos: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut char_ends = vec![];
+    for chunk in line.utf8_chunks() {
+        let start = char_ends.last().copied().unwrap_or(0);
+        char_ends.extend(
+            chunk
+                .valid()
+                .char_indices()
+                .map(|(i, c)| start + i + c.len_utf8()),
+        );
+        let start = start + chunk.valid().len();
+        char_ends.extend((1..=chunk.invalid().len()).map(|i| start + i));
+    }
+    let char_start = |n: usize| if n == 0 { 0 } else { char_ends[n - 1] };
+    bytes_pos
         .iter()
-        .cloned()
-        .flat_map(|range| range.filter_map(|i| bytes.get(i)).copied())
-        .collect();
-    String::from_utf8_lossy(&selected).into_owned()
+        .map(|range| {
+            let first = char_ends.partition_point(|&end| end <= range.start);
+            let last = char_ends.partition_point(|&end| end <= range.end);
+            char_start(first)..char_start(last).max(char_start(first))
+        })
+        .collect()
 }
 
 fn extract_fields(record: &StringRecord, field_pos: &[Range<usize>]) -> Vec<String> {
@@ -183,10 +1213,27 @@ fn extract_fields(record: &StringRecord, field_pos: &[Range<usize>]) -> Vec<Stri
 }
 
 #[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
 mod unit_tests {
-    use super::{extract_bytes, extract_chars, extract_fields, parse_pos};
+    use super::{
+        extract_chars, extract_fields, extract_raw_bytes, extract_units, glob_to_regex,
+        json_fields, parse_columns, parse_field_names, parse_pos, resolve_field_names, select,
+        snap_to_chars, split_chars, CharUnit, Position, Splitter,
+    };
     use csv::StringRecord;
     use pretty_assertions::assert_eq;
+    use regex::Regex;
+    use std::ops::Range;
+
+    fn closed(ranges: &[Range<usize>]) -> Vec<Position> {
+        ranges
+            .iter()
+            .map(|range| Position {
+                start: range.start,
+                end: Some(range.end),
+            })
+            .collect()
+    }
 
     #[test]
     fn test_parse_pos() {
@@ -248,9 +1295,6 @@ mod unit_tests {
         let res = parse_pos("1,".to_string());
         assert!(res.is_err());
 
-        let res = parse_pos("1-".to_string());
-        assert!(res.is_err());
-
         let res = parse_pos("1-1-1".to_string());
         assert!(res.is_err());
 
@@ -275,55 +1319,238 @@ mod unit_tests {
         // All the following are acceptable
         let res = parse_pos("1".to_string());
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..1]);
+        assert_eq!(res.unwrap(), closed(&[0..1]));
 
         let res = parse_pos("01".to_string());
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..1]);
+        assert_eq!(res.unwrap(), closed(&[0..1]));
 
         let res = parse_pos("1,3".to_string());
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..1, 2..3]);
+        assert_eq!(res.unwrap(), closed(&[0..1, 2..3]));
 
         let res = parse_pos("001,0003".to_string());
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..1, 2..3]);
+        assert_eq!(res.unwrap(), closed(&[0..1, 2..3]));
 
         let res = parse_pos("1-3".to_string());
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..3]);
+        assert_eq!(res.unwrap(), closed(&[0..3]));
 
         let res = parse_pos("0001-03".to_string());
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..3]);
+        assert_eq!(res.unwrap(), closed(&[0..3]));
 
         let res = parse_pos("1,7,3-5".to_string());
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![0..1, 6..7, 2..5]);
+        assert_eq!(res.unwrap(), closed(&[0..1, 6..7, 2..5]));
 
         let res = parse_pos("15,19-20".to_string());
         assert!(res.is_ok());
-        assert_eq!(res.unwrap(), vec![14..15, 18..20]);
+        assert_eq!(res.unwrap(), closed(&[14..15, 18..20]));
+
+        // Open-ended ranges
+        let res = parse_pos("1-".to_string());
+        assert_eq!(
+            res.unwrap(),
+            vec![Position {
+                start: 0,
+                end: None
+            }]
+        );
+
+        let res = parse_pos("-3,5-".to_string());
+        assert_eq!(
+            res.unwrap(),
+            vec![
+                Position {
+                    start: 0,
+                    end: Some(3)
+                },
+                Position {
+                    start: 4,
+                    end: None
+                }
+            ]
+        );
+
+        let res = parse_pos("-1".to_string());
+        assert_eq!(res.unwrap(), closed(&[0..1]));
+    }
+
+    #[test]
+    fn test_select() {
+        let pos = parse_pos("3-4,1,2-3,6-".to_string()).unwrap();
+        assert_eq!(select(&pos, 8, false, false), &[0..1, 1..4, 5..8]);
+        assert_eq!(select(&pos, 8, true, false), &[2..4, 0..1, 1..3, 5..8]);
+        assert_eq!(select(&pos, 3, false, false), &[0..1, 1..3]);
+        assert_eq!(select(&pos, 8, false, true), &[4..5]);
+        assert_eq!(select(&pos, 4, true, true), &[] as &[Range<usize>]);
+        let pos = parse_pos("2".to_string()).unwrap();
+        assert_eq!(select(&pos, 4, false, true), &[0..1, 2..4]);
+    }
+
+    #[test]
+    fn test_glob_to_regex() {
+        assert_eq!(glob_to_regex("addr_*"), "^addr_.*$");
+        assert_eq!(glob_to_regex("f?.x"), "^f.\\.x$");
+        assert_eq!(glob_to_regex("[!a-m]*"), "^[^a-m].*$");
+    }
+
+    #[test]
+    fn test_resolve_field_names() {
+        let headers = StringRecord::from(vec!["id", "name", "email", "addr_zip", "addr_city"]);
+        let names = parse_field_names("email,addr_*,/^(id|name)$/").unwrap();
+        assert_eq!(
+            resolve_field_names(&names, &headers).unwrap(),
+            closed(&[2..3, 3..4, 4..5, 0..1, 1..2])
+        );
+        let names = parse_field_names("name,*").unwrap();
+        assert_eq!(
+            resolve_field_names(&names, &headers).unwrap(),
+            closed(&[1..2, 0..1, 2..3, 3..4, 4..5])
+        );
+        let names = parse_field_names("phone").unwrap();
+        assert_eq!(
+            resolve_field_names(&names, &headers)
+                .unwrap_err()
+                .to_string(),
+            r#"no field matches "phone""#
+        );
+        assert!(parse_field_names("name,,email").is_err());
+        assert!(parse_field_names("/(/").is_err());
+    }
+
+    #[test]
+    fn test_split() {
+        let literal = Splitter::Literal("::".to_string());
+        assert_eq!(literal.split("a::b:c::"), ["a", "b:c", ""]);
+        assert!(!literal.is_delimited("a:b"));
+        let regex = Splitter::Regex(Regex::new("[,;]+").unwrap());
+        assert_eq!(regex.split("a,;b;c"), ["a", "b", "c"]);
+        assert_eq!(Splitter::Whitespace.split("  a \t b  "), ["a", "b"]);
+        assert!(Splitter::Whitespace.split("  ").is_empty());
+    }
+
+    #[test]
+    fn test_parse_columns() {
+        let columns = parse_columns("id:1-8, name : 9-").unwrap();
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns[0].name, "id");
+        assert_eq!(columns[0].pos, closed(&[0..8])[0]);
+        assert_eq!(columns[1].name, "name");
+        assert_eq!(
+            columns[1].pos,
+            Position {
+                start: 8,
+                end: None
+            }
+        );
+
+        let res = parse_columns("id:1-8,name");
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            r#"invalid column spec: "name""#
+        );
+        let res = parse_columns(":1-8");
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            r#"invalid column spec: ":1-8""#
+        );
+        let res = parse_columns("id:8-1");
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            r#"invalid column spec: "id:8-1": First number in range (8) must be lower than second number (1)"#
+        );
+        let res = parse_columns("a:1,b:2\na:3");
+        assert_eq!(res.unwrap_err().to_string(), "duplicate column name 'a'");
+        let res = parse_columns("# nothing");
+        assert_eq!(res.unwrap_err().to_string(), "no columns given");
+    }
+
+    #[test]
+    fn test_json_fields() {
+        let line = r#"{"a":"x","b":{"c":[1,null]},"d":true}"#;
+        let keys = ["a", "/b/c/0", "/b/c/1", "b", "d", "missing"];
+        assert_eq!(
+            json_fields(line, &keys).unwrap(),
+            vec!["x", "1", "", r#"{"c":[1,null]}"#, "true", ""]
+        );
+        assert!(json_fields("a,b", &keys).is_err());
+    }
+
+    #[test]
+    fn test_split_chars() {
+        let family = "👨\u{200d}👩\u{200d}👧";
+        let line = format!("e\u{301}{}漢字x", family);
+        let (units, len) = split_chars(&line, CharUnit::Scalar);
+        assert_eq!(len, 10);
+        assert_eq!(units[1], (1, "\u{301}"));
+        let (units, len) = split_chars(&line, CharUnit::Grapheme);
+        assert_eq!(len, 5);
+        assert_eq!(units[..2], [(0, "e\u{301}"), (1, family)]);
+        let (units, len) = split_chars(&line, CharUnit::DisplayColumn);
+        assert_eq!(len, 8);
+        assert_eq!(units[2..], [(3, "漢"), (5, "字"), (7, "x")]);
+    }
+
+    #[test]
+    fn test_extract_units() {
+        let (units, _) = split_chars("a漢字b", CharUnit::DisplayColumn);
+        assert_eq!(extract_units(&units, &[0..3], ""), "a漢");
+        assert_eq!(extract_units(&units, &[2..4], ""), "字");
+        assert_eq!(extract_units(&units, &[5..6, 0..1], ":"), "b:a");
+        assert_eq!(extract_units(&units, &[6..9], ":"), "");
     }
 
     #[test]
     fn test_extract_chars() {
-        assert_eq!(extract_chars("", &[0..1]), "".to_string());
-        assert_eq!(extract_chars("ábc", &[0..1]), "á".to_string());
-        assert_eq!(extract_chars("ábc", &[0..1, 2..3]), "ác".to_string());
-        assert_eq!(extract_chars("ábc", &[0..3]), "ábc".to_string());
-        assert_eq!(extract_chars("ábc", &[2..3, 1..2]), "cb".to_string());
-        assert_eq!(extract_chars("ábc", &[0..1, 1..2, 4..5]), "áb".to_string());
+        assert_eq!(extract_chars("", &[0..1], ""), "".to_string());
+        assert_eq!(extract_chars("ábc", &[0..1], ""), "á".to_string());
+        assert_eq!(extract_chars("ábc", &[0..1, 2..3], ""), "ác".to_string());
+        assert_eq!(extract_chars("ábc", &[0..3], ""), "ábc".to_string());
+        assert_eq!(extract_chars("ábc", &[2..3, 1..2], ""), "cb".to_string());
+        assert_eq!(
+            extract_chars("ábc", &[0..1, 1..2, 4..5], ""),
+            "áb".to_string()
+        );
+        assert_eq!(extract_chars("ábc", &[0..1, 2..3], ":"), "á:c".to_string());
+        assert_eq!(extract_chars("ábc", &[0..1, 4..5], ":"), "á".to_string());
+    }
+
+    #[test]
+    fn test_extract_raw_bytes() {
+        let line = b"a\xffb\xe1\xbd";
+        assert_eq!(
+            extract_raw_bytes(line, &[1..2, 3..5], b":"),
+            b"\xff:\xe1\xbd"
+        );
+        assert_eq!(extract_raw_bytes(line, &[4..9], b""), b"\xbd");
+
+        let line = "ábc".as_bytes();
+        assert_eq!(extract_raw_bytes(line, &[0..1], b""), b"\xc3");
+        assert_eq!(extract_raw_bytes(line, &[0..2], b""), "á".as_bytes());
+        assert_eq!(extract_raw_bytes(line, &[0..3], b""), "áb".as_bytes());
+        assert_eq!(extract_raw_bytes(line, &[0..4], b""), "ábc".as_bytes());
+        assert_eq!(extract_raw_bytes(line, &[3..4, 2..3], b""), b"cb");
+        assert_eq!(extract_raw_bytes(line, &[0..2, 5..6], b""), "á".as_bytes());
+        assert_eq!(
+            extract_raw_bytes(line, &[0..2, 3..4], b", "),
+            "á, c".as_bytes()
+        );
     }
 
     #[test]
-    fn test_extract_bytes() {
-        assert_eq!(extract_bytes("ábc", &[0..1]), "�".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..2]), "á".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..3]), "áb".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..4]), "ábc".to_string());
-        assert_eq!(extract_bytes("ábc", &[3..4, 2..3]), "cb".to_string());
-        assert_eq!(extract_bytes("ábc", &[0..2, 5..6]), "á".to_string());
+    fn test_snap_to_chars() {
+        // "á" and "€" are bytes 0..2 and 3..6.
+        let line = "áb€".as_bytes();
+        assert_eq!(snap_to_chars(line, &[0..1]), [0..0]);
+        assert_eq!(snap_to_chars(line, &[0..2]), [0..2]);
+        assert_eq!(snap_to_chars(line, &[1..2]), [0..2]);
+        assert_eq!(snap_to_chars(line, &[1..5]), [0..3]);
+        assert_eq!(snap_to_chars(line, &[2..6]), [2..6]);
+        assert_eq!(snap_to_chars(line, &[4..10]), [3..6]);
+        assert_eq!(snap_to_chars(b"\xffa\xc3", &[0..1, 2..3]), [0..1, 2..3]);
     }
 
     #[test]
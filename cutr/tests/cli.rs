@@ -9,6 +9,16 @@ const PRG: &str = "cutr";
 const CSV: &str = "tests/inputs/movies1.csv";
 const TSV: &str = "tests/inputs/movies1.tsv";
 const BOOKS: &str = "tests/inputs/books.tsv";
+const RAGGED: &str = "tests/inputs/ragged.tsv";
+const PEOPLE: &str = "tests/inputs/people.csv";
+const QUOTES: &str = "tests/inputs/quotes.tsv";
+const DELIMS: &str = "tests/inputs/delims.txt";
+const FIXED: &str = "tests/inputs/fixed.txt";
+const LOG: &str = "tests/inputs/log.jsonl";
+const UNICODE: &str = "tests/inputs/unicode.txt";
+const LATIN1: &str = "tests/inputs/latin1.txt";
+const ORDERS: &str = "tests/inputs/orders.csv";
+const NAMES: &str = "tests/inputs/names.csv";
 
 // --------------------------------------------------
 fn random_string() -> String {
@@ -58,7 +68,7 @@ fn dies_not_enough_args() -> Result<()> {
     dies(
         &[CSV],
         "the following required arguments were not provided:\n  \
-        <--fields <FIELDS>|--bytes <BYTES>|--chars <CHARS>>",
+        <--fields <FIELDS>|--field-names <NAMES>|--bytes <BYTES>|--chars <CHARS>|--expr <EXPRS>|--columns <SPEC>>",
     )
 }
 
@@ -97,7 +107,7 @@ fn dies_bad_digit_chars() -> Result<()> {
 fn dies_empty_delimiter() -> Result<()> {
     dies(
         &[CSV, "-f", "1", "-d", ""],
-        r#"--delim "" must be a single byte"#,
+        r#"--delim "" must not be empty"#,
     )
 }
 
@@ -105,7 +115,7 @@ fn dies_empty_delimiter() -> Result<()> {
 #[test]
 fn dies_bad_delimiter() -> Result<()> {
     dies(
-        &[CSV, "-f", "1", "-d", ",,"],
+        &[CSV, "-f", "1", "--format", "csv", "-d", ",,"],
         r#"--delim ",," must be a single byte"#,
     )
 }
@@ -173,6 +183,15 @@ fn run_lossy(args: &[&str], expected_file: &str) -> Result<()> {
     Ok(())
 }
 
+// --------------------------------------------------
+fn run_bytes(args: &[&str], expected_file: &str) -> Result<()> {
+    let expected = fs::read(expected_file)?;
+    let output = Command::cargo_bin(PRG)?.args(args).output().expect("fail");
+    assert!(output.status.success());
+    assert_eq!(output.stdout, expected);
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn tsv_f1() -> Result<()> {
@@ -338,5 +357,629 @@ fn tsv_c1_8() -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn repeated_value() -> Result<()> {
-    run(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1,1.out")
+    run(
+        &[BOOKS, "-c", "1,1", "--keep-order"],
+        "tests/expected/books.c1,1.out",
+    )?;
+    run(&[BOOKS, "-c", "1,1"], "tests/expected/books.c1.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn output_delimiter() -> Result<()> {
+    run(
+        &[TSV, "-f", "1,3", "--output-delimiter", " | "],
+        "tests/expected/movies1.tsv.f1,3.od.out",
+    )?;
+    run(
+        &[TSV, "-c", "2,5-6", "--output-delimiter", ":"],
+        "tests/expected/movies1.tsv.c2,5-6.od.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn complement() -> Result<()> {
+    run(
+        &[TSV, "-f", "2", "--complement"],
+        "tests/expected/movies1.tsv.f2.complement.out",
+    )?;
+    run(
+        &[
+            TSV,
+            "-c",
+            "1-3,5",
+            "--complement",
+            "--output-delimiter",
+            ":",
+        ],
+        "tests/expected/movies1.tsv.c1-3,5.complement.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn only_delimited() -> Result<()> {
+    run(&[RAGGED, "-f", "2"], "tests/expected/ragged.tsv.f2.out")?;
+    run(
+        &[RAGGED, "-f", "2", "-s"],
+        "tests/expected/ragged.tsv.f2.s.out",
+    )?;
+    run(
+        &[RAGGED, "-f", "2", "--complement", "--only-delimited"],
+        "tests/expected/ragged.tsv.f2.complement.s.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn zero_terminated() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-d", ",", "-f", "2"])
+        .write_stdin("a,b\0c,d\n\0")
+        .assert()
+        .success()
+        .stdout("b\0d\n\0");
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-c", "2"])
+        .write_stdin("xyz\0abc")
+        .assert()
+        .success()
+        .stdout("y\0b\0");
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-f", "2", "--output-format", "jsonl"])
+        .write_stdin("a\tb\0c\td\0")
+        .assert()
+        .success()
+        .stdout("{\"f2\":\"b\"}\0{\"f2\":\"d\"}\0");
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-f", "2", "--output-format", "jsonl-arrays"])
+        .write_stdin("a\tb\0")
+        .assert()
+        .success()
+        .stdout("[\"b\"]\0");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn open_ended_ranges() -> Result<()> {
+    run(&[TSV, "-f", "2-"], "tests/expected/movies1.tsv.f2-.out")?;
+    run(&[TSV, "-f", "-2"], "tests/expected/movies1.tsv.f-2.out")?;
+    run(
+        &[TSV, "-b", "3-6,1-4,10-"],
+        "tests/expected/movies1.tsv.b3-6,1-4,10-.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn input_order() -> Result<()> {
+    run(&[TSV, "-f", "3,1"], "tests/expected/movies1.tsv.f3,1.out")?;
+    Command::cargo_bin(PRG)?
+        .args([TSV, "-f", "3,1", "--keep-order"])
+        .assert()
+        .success()
+        .stdout(
+            "director\ttitle\n\
+             John Landis\tThe Blues Brothers\n\
+             Tom Hooper\tLes Misérables\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn field_names() -> Result<()> {
+    run(
+        &[PEOPLE, "--format", "csv", "-F", "name,email,city"],
+        "tests/expected/people.csv.Fname,email,city.out",
+    )?;
+    run(
+        &[PEOPLE, "--format", "csv", "--field-names", "city,addr_*"],
+        "tests/expected/people.csv.Fcity,addr_.out",
+    )?;
+    run(
+        &[
+            PEOPLE,
+            "--format",
+            "csv",
+            "-F",
+            "/^(id|e)/",
+            "--output-delimiter",
+            " | ",
+        ],
+        "tests/expected/people.csv.Fregex.od.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn header() -> Result<()> {
+    run(
+        &[PEOPLE, "--format", "csv", "--header", "-f", "3,1"],
+        "tests/expected/people.csv.f3,1.header.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn format() -> Result<()> {
+    run(
+        &["tests/inputs/books.csv", "-d", ",", "-f", "3"],
+        "tests/expected/books.csv.f3.dcomma.out",
+    )?;
+    run(
+        &["tests/inputs/books.csv", "--format", "csv", "-f", "3"],
+        "tests/expected/books.csv.f3.csv.out",
+    )?;
+    run(
+        &[QUOTES, "--format", "plain", "-f", "2"],
+        "tests/expected/quotes.tsv.f2.out",
+    )?;
+    run(
+        &[QUOTES, "--format", "tsv", "-f", "2"],
+        "tests/expected/quotes.tsv.f2.tsv.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn multi_byte_delimiter() -> Result<()> {
+    run(
+        &[DELIMS, "-d", "||", "-f", "2,3"],
+        "tests/expected/delims.txt.f2,3.dpipes.out",
+    )?;
+    run(
+        &[DELIMS, "-d", "¦", "-f", "2"],
+        "tests/expected/delims.txt.f2.dbroken.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn regex_delimiter() -> Result<()> {
+    run(
+        &[DELIMS, "--regex-delimiter", r"\|\||::|¦", "-f", "2"],
+        "tests/expected/delims.txt.f2.regex.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn whitespace() -> Result<()> {
+    run(
+        &[DELIMS, "-w", "-f", "3,1"],
+        "tests/expected/delims.txt.f3,1.w.out",
+    )?;
+    run(
+        &[DELIMS, "--whitespace", "-f", "2", "-s"],
+        "tests/expected/delims.txt.f2.w.s.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_regex_delimiter() -> Result<()> {
+    dies(
+        &[DELIMS, "-f", "1", "--regex-delimiter", "x*"],
+        r#"--regex-delimiter "x*" must not match an empty string"#,
+    )?;
+    dies(
+        &[DELIMS, "-f", "1", "--format", "csv", "-w"],
+        "--regex-delimiter and --whitespace need --format=plain",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn columns() -> Result<()> {
+    run(
+        &[FIXED, "--columns", "id:1-8,name:9-34,amount:35-"],
+        "tests/expected/fixed.txt.columns.out",
+    )?;
+    run(
+        &[FIXED, "--columns", "amount:35-,id:7-8", "--byte-columns"],
+        "tests/expected/fixed.txt.columns.bytes.out",
+    )?;
+    run_bytes(
+        &[LATIN1, "--columns", "a:1-3,b:4-", "--byte-columns"],
+        "tests/expected/latin1.txt.columns.bytes.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn columns_output_format() -> Result<()> {
+    let spec = "@tests/inputs/fixed.spec";
+    run(
+        &[FIXED, "--columns", spec, "--trim", "--output-format", "csv"],
+        "tests/expected/fixed.txt.columns.trim.csv.out",
+    )?;
+    run(
+        &[FIXED, "--columns", spec, "--output-format", "tsv"],
+        "tests/expected/fixed.txt.columns.tsv.out",
+    )?;
+    run(
+        &[
+            FIXED,
+            "--columns",
+            spec,
+            "--trim",
+            "--output-format",
+            "json",
+        ],
+        "tests/expected/fixed.txt.columns.trim.json.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_columns() -> Result<()> {
+    dies(
+        &[FIXED, "--columns", "id:1-8,name"],
+        r#"invalid column spec: "name""#,
+    )?;
+    dies(
+        &[FIXED, "--columns", "@tests/inputs/missing.spec"],
+        "tests/inputs/missing.spec: No such file",
+    )?;
+    dies(
+        &[FIXED, "--columns", "a:1,a:2", "--output-format", "jsonl"],
+        "duplicate column name 'a'",
+    )?;
+    dies(
+        &[LATIN1, "--columns", "a:1-3,b:4-"],
+        "tests/inputs/latin1.txt: invalid utf-8 sequence",
+    )?;
+    dies(
+        &[FIXED, "-f", "1", "--trim"],
+        "the argument '--fields <FIELDS>' cannot be used with '--trim'",
+    )?;
+    for (flag, name) in [
+        ("--complement", "--complement"),
+        ("-s", "--only-delimited"),
+        ("--keep-order", "--keep-order"),
+    ] {
+        dies(
+            &[FIXED, "--columns", "id:1-8", flag],
+            &format!("the argument '--columns <SPEC>' cannot be used with '{name}'"),
+        )?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn jsonl_output() -> Result<()> {
+    run(
+        &[TSV, "-f", "3,1", "--output-format", "jsonl"],
+        "tests/expected/movies1.tsv.f3,1.jsonl.out",
+    )?;
+    run(
+        &[
+            PEOPLE,
+            "--format",
+            "csv",
+            "-F",
+            "name,city",
+            "--output-format",
+            "jsonl",
+        ],
+        "tests/expected/people.csv.Fname,city.jsonl.out",
+    )?;
+    run(
+        &[RAGGED, "-f", "2", "--output-format", "jsonl-arrays"],
+        "tests/expected/ragged.tsv.f2.jsonl-arrays.out",
+    )?;
+    run(
+        &[
+            TSV,
+            "tests/inputs/movies2.tsv",
+            "-f",
+            "1,2",
+            "--output-format",
+            "json",
+        ],
+        "tests/expected/movies1,2.tsv.f1,2.json.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn jsonl_input() -> Result<()> {
+    let keys = "level,msg,/req/id,/req/ms,tags";
+    run(
+        &[LOG, "--input-format", "jsonl", "--keys", keys, "-f", "1,3-"],
+        "tests/expected/log.jsonl.f1,3-.out",
+    )?;
+    run(
+        &[
+            LOG,
+            "--format",
+            "jsonl",
+            "--keys",
+            keys,
+            "-f",
+            "3,1",
+            "--keep-order",
+            "--output-format",
+            "jsonl",
+        ],
+        "tests/expected/log.jsonl.f3,1.jsonl.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn only_delimited_records() -> Result<()> {
+    run(
+        &[LOG, "--format", "jsonl", "--keys", "msg", "-f", "1", "-s"],
+        "tests/expected/log.jsonl.msg.f1.s.out",
+    )?;
+    run(
+        &[NAMES, "--format", "csv", "-F", "name", "-s"],
+        "tests/expected/names.csv.Fname.s.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_jsonl() -> Result<()> {
+    dies(
+        &[LOG, "--format", "jsonl", "-f", "1"],
+        "--format=jsonl needs --keys",
+    )?;
+    dies(
+        &[CSV, "--keys", "a", "-f", "1"],
+        "--keys needs --format=jsonl",
+    )?;
+    dies(
+        &[CSV, "--format", "jsonl", "--keys", "a", "-f", "1"],
+        "tests/inputs/movies1.csv: line 1: expected ident",
+    )?;
+    dies(
+        &[
+            LOG,
+            "--format",
+            "jsonl",
+            "--keys",
+            "msg",
+            "-f",
+            "1",
+            "--header",
+            "--output-format",
+            "jsonl",
+        ],
+        "--header can't be used with --format=jsonl",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn graphemes() -> Result<()> {
+    run(
+        &[UNICODE, "-c", "1-2"],
+        "tests/expected/unicode.txt.c1-2.out",
+    )?;
+    run(
+        &[UNICODE, "-c", "1-2", "--graphemes"],
+        "tests/expected/unicode.txt.c1-2.graphemes.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn display_columns() -> Result<()> {
+    run(
+        &[
+            UNICODE,
+            "-c",
+            "3-4,1",
+            "--display-columns",
+            "--output-delimiter",
+            "|",
+        ],
+        "tests/expected/unicode.txt.c3-4,1.display.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_no_split() -> Result<()> {
+    run(
+        &[BOOKS, "-b", "1,2-4", "-n"],
+        "tests/expected/books.b1,2-4.n.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes_raw() -> Result<()> {
+    run_lossy(&[LATIN1, "-b", "3-5"], "tests/expected/latin1.txt.b3-5.out")?;
+    run_bytes(
+        &[LATIN1, "-b", "3-5", "--raw"],
+        "tests/expected/latin1.txt.b3-5.out",
+    )?;
+    Command::cargo_bin(PRG)?
+        .args(["-b", "1-3"])
+        .write_stdin("ab\r\n")
+        .assert()
+        .success()
+        .stdout("ab\n");
+    Command::cargo_bin(PRG)?
+        .args(["-b", "1-3", "--raw"])
+        .write_stdin("ab\r\n")
+        .assert()
+        .success()
+        .stdout("ab\r\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn exprs() -> Result<()> {
+    run(
+        &[TSV, "-e", r#"$3,upper($1),$2 . "-" . $1,len($1)"#],
+        "tests/expected/movies1.tsv.exprs.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn exprs_one_field() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-e", "upper($1), len($1)"])
+        .write_stdin("abc\ndef\n")
+        .assert()
+        .success()
+        .stdout("ABC\t3\nDEF\t3\n");
+    run(
+        &[NAMES, "--format", "csv", "-e", "upper($name)"],
+        "tests/expected/names.csv.exprs.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn exprs_by_name() -> Result<()> {
+    run(
+        &[
+            PEOPLE,
+            "--format",
+            "csv",
+            "-e",
+            r#"upper($name),$id . ":" . ${city}"#,
+        ],
+        "tests/expected/people.csv.exprs.out",
+    )?;
+    run(
+        &[
+            PEOPLE,
+            "--format",
+            "csv",
+            "--expr",
+            "$2,len($email)",
+            "--output-format",
+            "jsonl",
+        ],
+        "tests/expected/people.csv.exprs.jsonl.out",
+    )?;
+    run(
+        &[
+            LOG,
+            "--format",
+            "jsonl",
+            "--keys",
+            "level,msg",
+            "-e",
+            "upper($level),$msg",
+        ],
+        "tests/expected/log.jsonl.exprs.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_expr() -> Result<()> {
+    dies(
+        &[TSV, "-e", "upper($1"],
+        r#"invalid expression "upper($1": expected ")" at 8"#,
+    )?;
+    dies(
+        &[PEOPLE, "--format", "csv", "-e", "$phone"],
+        r#"tests/inputs/people.csv: no field matches "phone""#,
+    )?;
+    for (flag, name) in [
+        ("--complement", "--complement"),
+        ("-s", "--only-delimited"),
+        ("--keep-order", "--keep-order"),
+    ] {
+        dies(
+            &[TSV, "-e", "$1", flag],
+            &format!("the argument '--expr <EXPRS>' cannot be used with '{name}'"),
+        )?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn filter() -> Result<()> {
+    run(
+        &[
+            ORDERS,
+            "--format",
+            "csv",
+            "-f",
+            "1,3",
+            "--where",
+            "$3 > 100 && $2 ~ /^EU/",
+        ],
+        "tests/expected/orders.csv.where.out",
+    )?;
+    run(
+        &[
+            ORDERS,
+            "--format",
+            "csv",
+            "-F",
+            "id,note",
+            "--where",
+            "$amount < 100 || empty($amount)",
+        ],
+        "tests/expected/orders.csv.where.names.out",
+    )?;
+    run(
+        &[
+            ORDERS,
+            "-d",
+            ",",
+            "-f",
+            "1",
+            "--header",
+            "--where",
+            "!empty($note) && $region !~ /US/",
+        ],
+        "tests/expected/orders.csv.where.plain.out",
+    )?;
+    for filter in ["$msg ~ /s/", "$2 ~ /s/"] {
+        run(
+            &[
+                LOG,
+                "--format",
+                "jsonl",
+                "--keys",
+                "level,msg",
+                "-f",
+                "1",
+                "--where",
+                filter,
+            ],
+            "tests/expected/log.jsonl.f1.where.out",
+        )?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_filter() -> Result<()> {
+    dies(
+        &[ORDERS, "-f", "1", "--where", "$1 >"],
+        r#"invalid condition "$1 >": expected a value at 4"#,
+    )?;
+    dies(
+        &[ORDERS, "-f", "1", "--where", "$nope > 1"],
+        r#"tests/inputs/orders.csv: no field matches "nope""#,
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_unknown_field_name() -> Result<()> {
+    dies(
+        &[PEOPLE, "--format", "csv", "-F", "name,phone"],
+        r#"tests/inputs/people.csv: no field matches "phone""#,
+    )
 }
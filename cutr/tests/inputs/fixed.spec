@@ -0,0 +1,5 @@
+# mainframe export
+id:1-8
+name:9-34
+
+amount:35-